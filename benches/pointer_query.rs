@@ -0,0 +1,108 @@
+#![allow(clippy::needless_return, clippy::unused_unit)]
+
+/**
+ * Benchmark of the pointer queries of the data collector.
+ *
+ * A minimal X server answering `QueryPointer` requests runs in a background
+ * thread, so that the benchmark does not need a display. The throughput is
+ * reported in events per second, waiting for the reply of a pointer query
+ * after every event, sending the queries pipelined, taking the position from
+ * the pointer events as the collector does, and falling back to a single
+ * query per drain when the pointer events are missing.
+ */
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use x11rb::protocol::xproto;
+use x11rb::rust_connection::RustConnection;
+
+// The benchmark shares the modules with the collector and its tests, but
+// only uses a part of them.
+#[allow(dead_code)]
+#[path = "../src/data_collector/pointer.rs"]
+mod pointer;
+#[allow(dead_code)]
+#[path = "../src/test_x_server.rs"]
+mod test_x_server;
+
+use test_x_server::ROOT_WINDOW;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Number of events in a burst of events.
+const EVENTS: u64 = 1000;
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Wait for the reply of the pointer query after every event.
+fn query_each(connection: &RustConnection) -> () {
+    for _event in 0..EVENTS {
+        let cookie = xproto::query_pointer(connection, ROOT_WINDOW).unwrap();
+        cookie.reply().unwrap();
+    }
+}
+
+/// Send the pointer queries of the events without waiting for the replies.
+fn query_pipelined(connection: &RustConnection) -> () {
+    let cookies: Vec<_> = (0..EVENTS)
+        .map(|_event| xproto::query_pointer(connection, ROOT_WINDOW).unwrap())
+        .collect();
+    for cookie in cookies {
+        cookie.reply().unwrap();
+    }
+}
+
+/// Take the positions from the pointer events following the raw events.
+fn track_pointer_events(connection: &RustConnection) -> () {
+    let mut queue = pointer::PointerQueue::new(connection, ROOT_WINDOW);
+    for event in 0..EVENTS {
+        if queue.must_drain(Some(true)) {
+            queue.drain(|_event, _pointer| ());
+        }
+        let time = event as u32;
+        queue.push(event, time);
+        let position = pointer::Pointer {
+            root_x: 0,
+            root_y: 0,
+            child: x11rb::NONE,
+        };
+        queue.update(time, position);
+    }
+    queue.drain(|_event, _pointer| ());
+}
+
+/// Query the pointer once per drain for raw events without pointer events.
+fn query_per_drain(connection: &RustConnection) -> () {
+    let mut queue = pointer::PointerQueue::new(connection, ROOT_WINDOW);
+    for event in 0..EVENTS {
+        if queue.must_drain(Some(true)) {
+            queue.drain(|_event, _pointer| ());
+        }
+        queue.push(event, event as u32);
+    }
+    queue.drain(|_event, _pointer| ());
+}
+
+fn bench_pointer_query(criterion: &mut Criterion) -> () {
+    let connection = test_x_server::connect();
+    let mut group = criterion.benchmark_group("pointer_query");
+    group.throughput(Throughput::Elements(EVENTS));
+    group.bench_function("round_trip_per_event", |bencher| {
+        bencher.iter(|| query_each(&connection))
+    });
+    group.bench_function("pipelined", |bencher| {
+        bencher.iter(|| query_pipelined(&connection))
+    });
+    group.bench_function("pointer_events", |bencher| {
+        bencher.iter(|| track_pointer_events(&connection))
+    });
+    group.bench_function("query_per_drain", |bencher| {
+        bencher.iter(|| query_per_drain(&connection))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_pointer_query);
+criterion_main!(benches);
@@ -2,7 +2,7 @@
  * This module provides configurational constants and default values for the
  * application.
  */
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::env;
 use std::str::FromStr;
 
@@ -13,15 +13,73 @@ use std::str::FromStr;
 const DEFAULT_APP_API_KEY_NAME: &str = "api-key";
 const DEFAULT_APP_API_KEY_VALUE: &str = "x11-sentinel-client";
 const DEFAULT_APP_BUFFER_SIZE_LIMIT: usize = 100;
+const DEFAULT_APP_CONTROL_SOCKET: &str = "x11-sentinel-client.sock";
+const DEFAULT_APP_DBUS_SERVICE_ENABLED: bool = true;
+const DEFAULT_APP_EXCLUDE_EXECUTABLES: &str = "";
+const DEFAULT_APP_EXCLUDE_GAP_MARKER: bool = true;
+const DEFAULT_APP_EXCLUDE_TITLE: &str = "";
+const DEFAULT_APP_EXCLUDE_WM_CLASSES: &str = "";
+const DEFAULT_APP_FOCUS_ALLOWED_WM_CLASSES: &str = "";
+const DEFAULT_APP_FOCUS_EVENTS_ENABLED: bool = false;
+const DEFAULT_APP_HEALTH_ADDRESS: &str = "";
 const DEFAULT_APP_IDLE_TIMEOUT: u64 = 10000;
+const DEFAULT_APP_LOCK_ARGUMENTS: &str = "";
+const DEFAULT_APP_LOCK_BACKEND: &str = "command";
+const DEFAULT_APP_LOCK_COOLDOWN: u64 = 0;
 const DEFAULT_APP_LOCK_ENABLED: bool = false;
+const DEFAULT_APP_LOCK_POLICY: &str = "immediate";
+const DEFAULT_APP_LOCK_POLICY_WINDOW: usize = 3;
+const DEFAULT_APP_LOCK_REARM_THRESHOLD: f64 = 0.5;
 const DEFAULT_APP_LOCK_THRESHOLD: f64 = 0.5;
 const DEFAULT_APP_LOCK_UTILITY: &str = "slock";
-const DEFAULT_APP_METADATA_QUERY_INTERVAL: i64 = 600000;
+const DEFAULT_APP_METADATA_QUERY_INTERVAL: i64 = 3600000;
+const DEFAULT_APP_METRICS_ADDRESS: &str = "";
+const DEFAULT_APP_NOTIFICATION_ACTIONS: bool = false;
+const DEFAULT_APP_NOTIFICATION_SCORE_DELTA: f64 = 0.1;
+const DEFAULT_APP_NOTIFICATION_TIMEOUT: &str = "";
+const DEFAULT_APP_NOTIFICATION_URGENCY: &str = "verify=normal,*=low";
+const DEFAULT_APP_REAUTH_COMMAND: &str = "";
+const DEFAULT_APP_REAUTH_COOLDOWN: u64 = 300;
+const DEFAULT_APP_REAUTH_THRESHOLD: f64 = 0.0;
+const DEFAULT_APP_REMOTE_CONFIG_ENABLED: bool = true;
+const DEFAULT_APP_REMOTE_CONFIG_PINNED: &str = "";
 const DEFAULT_APP_STATUS_BASE_URL: &str = "http://localhost:3000/status";
 const DEFAULT_APP_STATUS_INTERVAL: u64 = 100;
+const DEFAULT_APP_STATUS_LONG_POLL_TIMEOUT: u64 = 0;
+const DEFAULT_APP_STATUS_PUSH_BASE_URL: &str = "";
 const DEFAULT_APP_SUBMIT_URL: &str = "http://localhost:3000/chunk";
+const DEFAULT_APP_TRAY_ENABLED: bool = false;
 const DEFAULT_APP_USER_ID: &str = "default_user";
+const DEFAULT_APP_WARN_COOLDOWN: u64 = 300;
+const DEFAULT_APP_WARN_THRESHOLD: f64 = 0.0;
+const DEFAULT_APP_WINDOW_COORDINATES_ENABLED: bool = false;
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Control the running instance via its control socket.
+    Ctl {
+        #[clap(subcommand)]
+        action: ControlAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, Copy)]
+pub enum ControlAction {
+    /// Pause the collection of input events.
+    Pause,
+    /// Resume the collection of input events.
+    Resume,
+    /// Submit the buffered events right away.
+    Flush,
+    /// Print the last status, the buffer size and the sequence number.
+    Status,
+    /// Query the metadata and the status, applying the remote configuration.
+    Reload,
+}
 
 //==============================================================================
 // Structs
@@ -43,29 +101,172 @@ pub struct Config {
     #[clap(long, value_parser)]
     pub buffer_size_limit: Option<usize>,
 
+    /// Command to execute instead of starting the data collection.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path of the Unix domain socket controlling the running instance.
+    /// Relative paths are relative to `$XDG_RUNTIME_DIR`, the control socket
+    /// is not served if it is not set. The control socket is disabled if the
+    /// path is empty.
+    #[clap(long, value_parser)]
+    pub control_socket: Option<String>,
+
+    /// Whether the application is exported on the session bus as
+    /// `org.x11sentinel.Client`.
+    #[clap(long, value_parser)]
+    pub dbus_service_enabled: Option<bool>,
+
+    /// Comma separated list of executables whose windows are excluded from
+    /// the collection, e.g. `keepassxc,/usr/bin/zoom`. Entries containing a
+    /// slash match the full path of the executable, others its file name.
+    #[clap(long, value_parser)]
+    pub exclude_executables: Option<String>,
+
+    /// Whether a gap event marks the time span in which input events were
+    /// dropped because an excluded window was in use.
+    #[clap(long, value_parser)]
+    pub exclude_gap_marker: Option<bool>,
+
+    /// Regular expression matching the titles of the windows excluded from
+    /// the collection.
+    #[clap(long, value_parser)]
+    pub exclude_title: Option<String>,
+
+    /// Comma separated list of WM_CLASS instance or class names excluded from
+    /// the collection, e.g. `KeePassXC,zoom`. Names are case insensitive.
+    #[clap(long, value_parser)]
+    pub exclude_wm_classes: Option<String>,
+
+    /// Comma separated list of case insensitive WM_CLASS class names reported
+    /// as they are by the active window changed events. Other class names are
+    /// hashed with a local secret key.
+    #[clap(long, value_parser)]
+    pub focus_allowed_wm_classes: Option<String>,
+
+    /// Whether the changes of the active window are reported.
+    #[clap(long, value_parser)]
+    pub focus_events_enabled: Option<bool>,
+
+    /// Local address of the HTTP endpoint reporting the health of the
+    /// application at `/health`, either a TCP address like `127.0.0.1:9101` or
+    /// the path of a Unix domain socket. The report is not served if the
+    /// address is empty.
+    #[clap(long, value_parser)]
+    pub health_address: Option<String>,
+
     /// If no new event is generated for this number of milliseconds, a
     /// submission gets triggered.
     #[clap(long, value_parser)]
     pub idle_timeout: Option<u64>,
 
+    /// Arguments passed to the session lock utility when the `command` lock
+    /// backend is used. The `{phase}`, `{description}`, `{score}` and
+    /// `{threshold}` placeholders are replaced with the values that triggered
+    /// the lock.
+    #[clap(long, value_parser)]
+    pub lock_arguments: Option<String>,
+
+    /// Backend used to lock the session. One of `command`, `loginctl`, `dbus`
+    /// or `xdg-screensaver`.
+    #[clap(long, value_parser)]
+    pub lock_backend: Option<String>,
+
+    /// Minimum number of seconds between two session locks.
+    #[clap(long, value_parser)]
+    pub lock_cooldown: Option<u64>,
+
     /// Whether X session locking functionality is enabled.
     #[clap(long, value_parser)]
     pub lock_enabled: Option<bool>,
 
+    /// Policy deciding whether the user's scores lock the session. One of
+    /// `immediate` (a single low score), `consecutive` (the last
+    /// `lock_policy_window` scores are all low) or `average` (the average of
+    /// the last `lock_policy_window` scores is low).
+    #[clap(long, value_parser)]
+    pub lock_policy: Option<String>,
+
+    /// Number of scores evaluated by the `consecutive` and `average` lock
+    /// policies.
+    #[clap(long, value_parser)]
+    pub lock_policy_window: Option<usize>,
+
+    /// After locking the session, the user's score has to reach this value
+    /// before the session can be locked again.
+    #[clap(long, value_parser)]
+    pub lock_rearm_threshold: Option<f64>,
+
     /// If the user's score is lower than this predefined constant and session
     /// locking is enabled, then the session locking utility is executed.
     #[clap(long, value_parser)]
     pub lock_threshold: Option<f64>,
 
     /// X session lock utility program that is used to lock the session when
-    /// needed.
+    /// needed by the `command` lock backend.
     #[clap(long, value_parser)]
     pub lock_utility: Option<String>,
 
-    /// Query interval of the platform specific metadata in milliseconds.
+    /// Query interval of the platform specific metadata in milliseconds. Monitor
+    /// and input device changes are reported immediately, this periodic query
+    /// only serves as a safety net.
     #[clap(long, value_parser)]
     pub metadata_query_interval: Option<i64>,
 
+    /// Local address of the HTTP endpoint exposing Prometheus metrics at
+    /// `/metrics`, e.g. `127.0.0.1:9100`. Metrics are not served if the
+    /// address is empty.
+    #[clap(long, value_parser)]
+    pub metrics_address: Option<String>,
+
+    /// Whether the status notification offers actions to pause the collection
+    /// and to show the status details.
+    #[clap(long, value_parser)]
+    pub notification_actions: Option<bool>,
+
+    /// The status notification is updated when the phase changes or the
+    /// user's score changes by at least this value.
+    #[clap(long, value_parser)]
+    pub notification_score_delta: Option<f64>,
+
+    /// Timeout of the status notification in milliseconds per phase, in the
+    /// `phase=timeout,...` format. The `*` phase matches every other phase, 0
+    /// means the notification never expires.
+    #[clap(long, value_parser)]
+    pub notification_timeout: Option<String>,
+
+    /// Urgency (`low`, `normal` or `critical`) of the status notification per
+    /// phase, in the `phase=urgency,...` format. The `*` phase matches every
+    /// other phase.
+    #[clap(long, value_parser)]
+    pub notification_urgency: Option<String>,
+
+    /// Command executed to re-authenticate the user when the user's score is
+    /// lower than `reauth_threshold`. The session gets locked if the command
+    /// fails. Re-authentication is disabled if the command is empty.
+    #[clap(long, value_parser)]
+    pub reauth_command: Option<String>,
+
+    /// Minimum number of seconds between two re-authentications.
+    #[clap(long, value_parser)]
+    pub reauth_cooldown: Option<u64>,
+
+    /// If the user's score is lower than this predefined constant, the
+    /// re-authentication command is executed.
+    #[clap(long, value_parser)]
+    pub reauth_threshold: Option<f64>,
+
+    /// Whether the remote server may change the `buffer_size_limit`,
+    /// `idle_timeout`, `lock_enabled`, `lock_threshold` and `status_interval`
+    /// settings via the status response.
+    #[clap(long, value_parser)]
+    pub remote_config_enabled: Option<bool>,
+
+    /// Comma separated list of settings that the remote server may not change,
+    /// e.g. `lock_enabled,lock_threshold`.
+    #[clap(long, value_parser)]
+    pub remote_config_pinned: Option<String>,
+
     /// Base URL of the status API endpoint.
     #[clap(long, value_parser)]
     pub status_base_url: Option<String>,
@@ -74,13 +275,44 @@ pub struct Config {
     #[clap(long, value_parser)]
     pub status_interval: Option<u64>,
 
+    /// Number of seconds the status API endpoint may hold a status request
+    /// until the status changes. Long polling is disabled if it is 0.
+    #[clap(long, value_parser)]
+    pub status_long_poll_timeout: Option<u64>,
+
+    /// Base URL of the Server-Sent Events endpoint pushing status updates. The
+    /// status is polled every `status_interval` seconds while the push channel
+    /// is unavailable. Pushing is disabled if the URL is empty.
+    #[clap(long, value_parser)]
+    pub status_push_base_url: Option<String>,
+
     /// URL of the submission API endpoint.
     #[clap(long, value_parser)]
     pub submit_url: Option<String>,
 
+    /// Whether a tray icon shows the phase and the user's score, with a menu
+    /// to pause the collection and to show the last status. Requires a
+    /// StatusNotifierItem host, e.g. a KDE or a GNOME shell extension.
+    #[clap(long, value_parser)]
+    pub tray_enabled: Option<bool>,
+
     /// Unique identifier of the user
     #[clap(long, value_parser)]
     pub user_id: Option<String>,
+
+    /// Minimum number of seconds between two warning notifications.
+    #[clap(long, value_parser)]
+    pub warn_cooldown: Option<u64>,
+
+    /// If the user's score is lower than this predefined constant, a warning
+    /// notification is shown.
+    #[clap(long, value_parser)]
+    pub warn_threshold: Option<f64>,
+
+    /// Whether pointer events carry the coordinates relative to the window
+    /// under the pointer and the size of the window.
+    #[clap(long, value_parser)]
+    pub window_coordinates_enabled: Option<bool>,
 }
 
 impl Config {
@@ -90,15 +322,46 @@ impl Config {
         config.set_api_key_name();
         config.set_api_key_value();
         config.set_buffer_size_limit();
+        config.set_control_socket();
+        config.set_dbus_service_enabled();
+        config.set_exclude_executables();
+        config.set_exclude_gap_marker();
+        config.set_exclude_title();
+        config.set_exclude_wm_classes();
+        config.set_focus_allowed_wm_classes();
+        config.set_focus_events_enabled();
+        config.set_health_address();
         config.set_idle_timeout();
+        config.set_lock_arguments();
+        config.set_lock_backend();
+        config.set_lock_cooldown();
         config.set_lock_enabled();
+        config.set_lock_policy();
+        config.set_lock_policy_window();
+        config.set_lock_rearm_threshold();
         config.set_lock_threshold();
         config.set_lock_utility();
         config.set_metadata_query_interval();
+        config.set_metrics_address();
+        config.set_notification_actions();
+        config.set_notification_score_delta();
+        config.set_notification_timeout();
+        config.set_notification_urgency();
+        config.set_reauth_command();
+        config.set_reauth_cooldown();
+        config.set_reauth_threshold();
+        config.set_remote_config_enabled();
+        config.set_remote_config_pinned();
         config.status_base_url();
         config.set_status_interval();
+        config.set_status_long_poll_timeout();
+        config.set_status_push_base_url();
         config.set_submit_url();
+        config.set_tray_enabled();
         config.set_user_id();
+        config.set_warn_cooldown();
+        config.set_warn_threshold();
+        config.set_window_coordinates_enabled();
         return config;
     }
 
@@ -141,6 +404,123 @@ impl Config {
         }
     }
 
+    /// Setter method for the `control_socket` field.
+    fn set_control_socket(&mut self) -> () {
+        match &self.control_socket {
+            Some(_value) => (),
+            None => {
+                self.control_socket = Some(get_env_var_or(
+                    "APP_CONTROL_SOCKET",
+                    DEFAULT_APP_CONTROL_SOCKET.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `dbus_service_enabled` field.
+    fn set_dbus_service_enabled(&mut self) -> () {
+        match &self.dbus_service_enabled {
+            Some(_value) => (),
+            None => {
+                self.dbus_service_enabled = Some(get_env_var_or(
+                    "APP_DBUS_SERVICE_ENABLED",
+                    DEFAULT_APP_DBUS_SERVICE_ENABLED,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `exclude_executables` field.
+    fn set_exclude_executables(&mut self) -> () {
+        match &self.exclude_executables {
+            Some(_value) => (),
+            None => {
+                self.exclude_executables = Some(get_env_var_or(
+                    "APP_EXCLUDE_EXECUTABLES",
+                    DEFAULT_APP_EXCLUDE_EXECUTABLES.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `exclude_gap_marker` field.
+    fn set_exclude_gap_marker(&mut self) -> () {
+        match &self.exclude_gap_marker {
+            Some(_value) => (),
+            None => {
+                self.exclude_gap_marker = Some(get_env_var_or(
+                    "APP_EXCLUDE_GAP_MARKER",
+                    DEFAULT_APP_EXCLUDE_GAP_MARKER,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `exclude_title` field.
+    fn set_exclude_title(&mut self) -> () {
+        match &self.exclude_title {
+            Some(_value) => (),
+            None => {
+                self.exclude_title = Some(get_env_var_or(
+                    "APP_EXCLUDE_TITLE",
+                    DEFAULT_APP_EXCLUDE_TITLE.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `exclude_wm_classes` field.
+    fn set_exclude_wm_classes(&mut self) -> () {
+        match &self.exclude_wm_classes {
+            Some(_value) => (),
+            None => {
+                self.exclude_wm_classes = Some(get_env_var_or(
+                    "APP_EXCLUDE_WM_CLASSES",
+                    DEFAULT_APP_EXCLUDE_WM_CLASSES.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `focus_allowed_wm_classes` field.
+    fn set_focus_allowed_wm_classes(&mut self) -> () {
+        match &self.focus_allowed_wm_classes {
+            Some(_value) => (),
+            None => {
+                self.focus_allowed_wm_classes = Some(get_env_var_or(
+                    "APP_FOCUS_ALLOWED_WM_CLASSES",
+                    DEFAULT_APP_FOCUS_ALLOWED_WM_CLASSES.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `focus_events_enabled` field.
+    fn set_focus_events_enabled(&mut self) -> () {
+        match &self.focus_events_enabled {
+            Some(_value) => (),
+            None => {
+                self.focus_events_enabled = Some(get_env_var_or(
+                    "APP_FOCUS_EVENTS_ENABLED",
+                    DEFAULT_APP_FOCUS_EVENTS_ENABLED,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `health_address` field.
+    fn set_health_address(&mut self) -> () {
+        match &self.health_address {
+            Some(_value) => (),
+            None => {
+                self.health_address = Some(get_env_var_or(
+                    "APP_HEALTH_ADDRESS",
+                    DEFAULT_APP_HEALTH_ADDRESS.to_string(),
+                ))
+            }
+        }
+    }
+
     /// Setter method for the `idle_timeout` field.
     fn set_idle_timeout(&mut self) -> () {
         match &self.idle_timeout {
@@ -152,6 +532,45 @@ impl Config {
         }
     }
 
+    /// Setter method for the `lock_arguments` field.
+    fn set_lock_arguments(&mut self) -> () {
+        match &self.lock_arguments {
+            Some(_value) => (),
+            None => {
+                self.lock_arguments = Some(get_env_var_or(
+                    "APP_LOCK_ARGUMENTS",
+                    DEFAULT_APP_LOCK_ARGUMENTS.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `lock_backend` field.
+    fn set_lock_backend(&mut self) -> () {
+        match &self.lock_backend {
+            Some(_value) => (),
+            None => {
+                self.lock_backend = Some(get_env_var_or(
+                    "APP_LOCK_BACKEND",
+                    DEFAULT_APP_LOCK_BACKEND.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `lock_cooldown` field.
+    fn set_lock_cooldown(&mut self) -> () {
+        match &self.lock_cooldown {
+            Some(_value) => (),
+            None => {
+                self.lock_cooldown = Some(get_env_var_or(
+                    "APP_LOCK_COOLDOWN",
+                    DEFAULT_APP_LOCK_COOLDOWN,
+                ))
+            }
+        }
+    }
+
     /// Setter method for the `lock_enabled` field.
     fn set_lock_enabled(&mut self) -> () {
         match &self.lock_enabled {
@@ -163,6 +582,45 @@ impl Config {
         }
     }
 
+    /// Setter method for the `lock_policy` field.
+    fn set_lock_policy(&mut self) -> () {
+        match &self.lock_policy {
+            Some(_value) => (),
+            None => {
+                self.lock_policy = Some(get_env_var_or(
+                    "APP_LOCK_POLICY",
+                    DEFAULT_APP_LOCK_POLICY.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `lock_policy_window` field.
+    fn set_lock_policy_window(&mut self) -> () {
+        match &self.lock_policy_window {
+            Some(_value) => (),
+            None => {
+                self.lock_policy_window = Some(get_env_var_or(
+                    "APP_LOCK_POLICY_WINDOW",
+                    DEFAULT_APP_LOCK_POLICY_WINDOW,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `lock_rearm_threshold` field.
+    fn set_lock_rearm_threshold(&mut self) -> () {
+        match &self.lock_rearm_threshold {
+            Some(_value) => (),
+            None => {
+                self.lock_rearm_threshold = Some(get_env_var_or(
+                    "APP_LOCK_REARM_THRESHOLD",
+                    DEFAULT_APP_LOCK_REARM_THRESHOLD,
+                ))
+            }
+        }
+    }
+
     /// Setter method for the `lock_threshold` field.
     fn set_lock_threshold(&mut self) -> () {
         match &self.lock_threshold {
@@ -202,6 +660,136 @@ impl Config {
         }
     }
 
+    /// Setter method for the `metrics_address` field.
+    fn set_metrics_address(&mut self) -> () {
+        match &self.metrics_address {
+            Some(_value) => (),
+            None => {
+                self.metrics_address = Some(get_env_var_or(
+                    "APP_METRICS_ADDRESS",
+                    DEFAULT_APP_METRICS_ADDRESS.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `notification_actions` field.
+    fn set_notification_actions(&mut self) -> () {
+        match &self.notification_actions {
+            Some(_value) => (),
+            None => {
+                self.notification_actions = Some(get_env_var_or(
+                    "APP_NOTIFICATION_ACTIONS",
+                    DEFAULT_APP_NOTIFICATION_ACTIONS,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `notification_score_delta` field.
+    fn set_notification_score_delta(&mut self) -> () {
+        match &self.notification_score_delta {
+            Some(_value) => (),
+            None => {
+                self.notification_score_delta = Some(get_env_var_or(
+                    "APP_NOTIFICATION_SCORE_DELTA",
+                    DEFAULT_APP_NOTIFICATION_SCORE_DELTA,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `notification_timeout` field.
+    fn set_notification_timeout(&mut self) -> () {
+        match &self.notification_timeout {
+            Some(_value) => (),
+            None => {
+                self.notification_timeout = Some(get_env_var_or(
+                    "APP_NOTIFICATION_TIMEOUT",
+                    DEFAULT_APP_NOTIFICATION_TIMEOUT.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `notification_urgency` field.
+    fn set_notification_urgency(&mut self) -> () {
+        match &self.notification_urgency {
+            Some(_value) => (),
+            None => {
+                self.notification_urgency = Some(get_env_var_or(
+                    "APP_NOTIFICATION_URGENCY",
+                    DEFAULT_APP_NOTIFICATION_URGENCY.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `reauth_command` field.
+    fn set_reauth_command(&mut self) -> () {
+        match &self.reauth_command {
+            Some(_value) => (),
+            None => {
+                self.reauth_command = Some(get_env_var_or(
+                    "APP_REAUTH_COMMAND",
+                    DEFAULT_APP_REAUTH_COMMAND.to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `reauth_cooldown` field.
+    fn set_reauth_cooldown(&mut self) -> () {
+        match &self.reauth_cooldown {
+            Some(_value) => (),
+            None => {
+                self.reauth_cooldown = Some(get_env_var_or(
+                    "APP_REAUTH_COOLDOWN",
+                    DEFAULT_APP_REAUTH_COOLDOWN,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `reauth_threshold` field.
+    fn set_reauth_threshold(&mut self) -> () {
+        match &self.reauth_threshold {
+            Some(_value) => (),
+            None => {
+                self.reauth_threshold = Some(get_env_var_or(
+                    "APP_REAUTH_THRESHOLD",
+                    DEFAULT_APP_REAUTH_THRESHOLD,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `remote_config_enabled` field.
+    fn set_remote_config_enabled(&mut self) -> () {
+        match &self.remote_config_enabled {
+            Some(_value) => (),
+            None => {
+                self.remote_config_enabled = Some(get_env_var_or(
+                    "APP_REMOTE_CONFIG_ENABLED",
+                    DEFAULT_APP_REMOTE_CONFIG_ENABLED,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `remote_config_pinned` field.
+    fn set_remote_config_pinned(&mut self) -> () {
+        match &self.remote_config_pinned {
+            Some(_value) => (),
+            None => {
+                self.remote_config_pinned = Some(get_env_var_or(
+                    "APP_REMOTE_CONFIG_PINNED",
+                    DEFAULT_APP_REMOTE_CONFIG_PINNED.to_string(),
+                ))
+            }
+        }
+    }
+
     /// Setter method for the `status_base_url` field.
     fn status_base_url(&mut self) -> () {
         match &self.status_base_url {
@@ -228,6 +816,32 @@ impl Config {
         }
     }
 
+    /// Setter method for the `status_long_poll_timeout` field.
+    fn set_status_long_poll_timeout(&mut self) -> () {
+        match &self.status_long_poll_timeout {
+            Some(_value) => (),
+            None => {
+                self.status_long_poll_timeout = Some(get_env_var_or(
+                    "APP_STATUS_LONG_POLL_TIMEOUT",
+                    DEFAULT_APP_STATUS_LONG_POLL_TIMEOUT,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `status_push_base_url` field.
+    fn set_status_push_base_url(&mut self) -> () {
+        match &self.status_push_base_url {
+            Some(_value) => (),
+            None => {
+                self.status_push_base_url = Some(get_env_var_or(
+                    "APP_STATUS_PUSH_BASE_URL",
+                    DEFAULT_APP_STATUS_PUSH_BASE_URL.to_string(),
+                ))
+            }
+        }
+    }
+
     /// Setter method for the `submit_url` field.
     fn set_submit_url(&mut self) -> () {
         match &self.submit_url {
@@ -241,6 +855,17 @@ impl Config {
         }
     }
 
+    /// Setter method for the `tray_enabled` field.
+    fn set_tray_enabled(&mut self) -> () {
+        match &self.tray_enabled {
+            Some(_value) => (),
+            None => {
+                self.tray_enabled =
+                    Some(get_env_var_or("APP_TRAY_ENABLED", DEFAULT_APP_TRAY_ENABLED))
+            }
+        }
+    }
+
     /// Setter method for the `user_id` field.
     fn set_user_id(&mut self) -> () {
         match &self.user_id {
@@ -253,6 +878,45 @@ impl Config {
             }
         }
     }
+
+    /// Setter method for the `warn_cooldown` field.
+    fn set_warn_cooldown(&mut self) -> () {
+        match &self.warn_cooldown {
+            Some(_value) => (),
+            None => {
+                self.warn_cooldown = Some(get_env_var_or(
+                    "APP_WARN_COOLDOWN",
+                    DEFAULT_APP_WARN_COOLDOWN,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `warn_threshold` field.
+    fn set_warn_threshold(&mut self) -> () {
+        match &self.warn_threshold {
+            Some(_value) => (),
+            None => {
+                self.warn_threshold = Some(get_env_var_or(
+                    "APP_WARN_THRESHOLD",
+                    DEFAULT_APP_WARN_THRESHOLD,
+                ))
+            }
+        }
+    }
+
+    /// Setter method for the `window_coordinates_enabled` field.
+    fn set_window_coordinates_enabled(&mut self) -> () {
+        match &self.window_coordinates_enabled {
+            Some(_value) => (),
+            None => {
+                self.window_coordinates_enabled = Some(get_env_var_or(
+                    "APP_WINDOW_COORDINATES_ENABLED",
+                    DEFAULT_APP_WINDOW_COORDINATES_ENABLED,
+                ))
+            }
+        }
+    }
 }
 
 //==============================================================================
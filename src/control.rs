@@ -0,0 +1,284 @@
+/**
+ * This module implements the control socket of the running instance and the
+ * `ctl` subcommand talking to it.
+ *
+ * The client sends the name of an action in a single line, the instance
+ * answers with a single line of JSON. Failed actions are answered with an
+ * object holding an `error` field.
+ */
+use serde_json::json;
+use std::env;
+use std::fs;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::ControlAction;
+use crate::data_collector;
+use crate::status;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Number of seconds to wait for the other side of the control socket.
+const SOCKET_TIMEOUT: u64 = 10;
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Serve the control socket in a background thread. Nothing is served if the
+/// path is empty, or if it is relative and `$XDG_RUNTIME_DIR` is not set.
+pub fn serve(path: &str) -> () {
+    if path.is_empty() {
+        return;
+    }
+    let path = match get_socket_path(path) {
+        Ok(path) => path,
+        Err(error) => {
+            println!("Not serving the control socket: {}", error);
+            return;
+        }
+    };
+    if let Err(error) = remove_stale_socket(&path) {
+        println!("Not serving the control socket: {}", error);
+        return;
+    }
+    let listener = match bind(&path) {
+        Ok(listener) => listener,
+        Err(error) => {
+            println!(
+                "Could not bind control socket {}: {}",
+                path.display(),
+                error
+            );
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream.and_then(handle_connection) {
+                Ok(()) => (),
+                Err(error) => println!("Control connection failed: {}", error),
+            }
+        }
+    });
+}
+
+/// Execute the action on the running instance and print its answer. The
+/// process exits with a non-zero exit code if the action failed.
+pub fn run_client(path: &str, action: ControlAction) -> () {
+    let path = match get_socket_path(path) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            process::exit(1);
+        }
+    };
+    match request(&path, action) {
+        Ok(answer) => match answer.get("error") {
+            Some(error) => {
+                eprintln!("Error: {}", error);
+                process::exit(1);
+            }
+            None => println!("{}", serde_json::to_string_pretty(&answer).unwrap()),
+        },
+        Err(error) => {
+            eprintln!("Could not reach the running instance: {}", error);
+            process::exit(1);
+        }
+    }
+}
+
+/// Remove the socket left behind by a previous instance. Other files are
+/// left untouched. Sockets of other users are neither removed nor used.
+pub fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            check_owner(&metadata)?;
+            // Do not take over the socket of an instance that is still running.
+            if UnixStream::connect(path).is_ok() {
+                return Ok(());
+            }
+            match fs::remove_file(path) {
+                Ok(()) => (),
+                Err(error) => println!("Could not remove stale socket: {}", error),
+            }
+        }
+        _ => (),
+    }
+    return Ok(());
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Read an action from the connection and answer it.
+fn handle_connection(mut stream: UnixStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(SOCKET_TIMEOUT)))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let answer = match execute(line.trim()) {
+        Ok(answer) => answer,
+        Err(error) => json!({ "error": error }),
+    };
+    return writeln!(stream, "{}", answer);
+}
+
+/// Execute the action received on the control socket.
+fn execute(action: &str) -> Result<serde_json::Value, String> {
+    match action {
+        "pause" => {
+            data_collector::pause();
+            return Ok(json!({ "paused": true }));
+        }
+        "resume" => {
+            data_collector::resume();
+            return Ok(json!({ "paused": false }));
+        }
+        "flush" => {
+            data_collector::flush()?;
+            return Ok(json!({ "flushed": true }));
+        }
+        "status" => {
+            let state = data_collector::query_state()?;
+            return Ok(json!({
+                "paused": state.paused,
+                "bufferSize": state.buffer_size,
+                "sequenceNumber": state.sequence_number,
+                "status": status::last_status(),
+            }));
+        }
+        "reload" => {
+            data_collector::refresh_metadata()?;
+            status::poll();
+            return Ok(json!({ "reloaded": true }));
+        }
+        _ => return Err(format!("unknown action: {}", action)),
+    }
+}
+
+/// Send the action to the running instance and return its answer. Sockets
+/// of other users are not trusted.
+fn request(path: &Path, action: ControlAction) -> io::Result<serde_json::Value> {
+    check_owner(&fs::symlink_metadata(path)?)?;
+    let mut stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(Duration::from_secs(SOCKET_TIMEOUT)))?;
+    writeln!(stream, "{}", get_action_name(action))?;
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    return Ok(serde_json::from_str(&line)?);
+}
+
+/// Name of the action sent over the control socket.
+fn get_action_name(action: ControlAction) -> &'static str {
+    match action {
+        ControlAction::Pause => "pause",
+        ControlAction::Resume => "resume",
+        ControlAction::Flush => "flush",
+        ControlAction::Status => "status",
+        ControlAction::Reload => "reload",
+    }
+}
+
+/// Resolve relative socket paths against `$XDG_RUNTIME_DIR`.
+fn get_socket_path(path: &str) -> Result<PathBuf, String> {
+    return resolve_socket_path(path, env::var("XDG_RUNTIME_DIR").ok());
+}
+
+/// Resolve the socket path against the runtime directory. Relative paths are
+/// rejected without a runtime directory, as the shared temporary directory
+/// would let other users take the socket over.
+fn resolve_socket_path(path: &str, runtime_dir: Option<String>) -> Result<PathBuf, String> {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    match runtime_dir {
+        Some(runtime_dir) if !runtime_dir.is_empty() => {
+            return Ok(Path::new(&runtime_dir).join(path))
+        }
+        _ => {
+            return Err(format!(
+                "XDG_RUNTIME_DIR is not set, use an absolute control socket path instead of {}",
+                path.display()
+            ))
+        }
+    }
+}
+
+/// Bind the socket readable and writable only by the user running the
+/// application. The socket is created with these permissions, so it is never
+/// accessible by other users.
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    // The umask is shared by all threads, other files created meanwhile are
+    // restricted as well at worst.
+    let umask = unsafe { libc::umask(0o077) };
+    let result = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    return result;
+}
+
+/// Fail if the file belongs to another user.
+fn check_owner(metadata: &fs::Metadata) -> io::Result<()> {
+    let uid = unsafe { libc::geteuid() };
+    if metadata.uid() != uid {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("the socket belongs to the user {}", metadata.uid()),
+        ));
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn relative_paths_require_runtime_dir() {
+        assert_eq!(
+            resolve_socket_path("client.sock", Some(String::from("/run/user/1000"))),
+            Ok(PathBuf::from("/run/user/1000/client.sock"))
+        );
+        assert_eq!(
+            resolve_socket_path("/tmp/client.sock", None),
+            Ok(PathBuf::from("/tmp/client.sock"))
+        );
+        assert!(resolve_socket_path("client.sock", None).is_err());
+        assert!(resolve_socket_path("client.sock", Some(String::new())).is_err());
+    }
+
+    #[test]
+    fn socket_is_created_private() {
+        let dir = env::temp_dir().join(format!("x11-sentinel-control-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("client.sock");
+
+        let listener = bind(&path).unwrap();
+        let metadata = fs::symlink_metadata(&path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o077, 0);
+        assert!(check_owner(&metadata).is_ok());
+
+        // The socket of the running instance is kept.
+        remove_stale_socket(&path).unwrap();
+        assert!(path.exists());
+        drop(listener);
+        remove_stale_socket(&path).unwrap();
+        assert!(!path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+/**
+ * This module tracks the active window to report when the user switches
+ * applications.
+ *
+ * Applications are identified by the class name of their `WM_CLASS`
+ * property. Only allowed class names are reported as they are, the others are
+ * reported as their HMAC-SHA256 keyed with a random secret. The secret is
+ * kept in the user's state directory and never leaves the machine, so the
+ * server can neither reverse the identifiers nor compare them across users.
+ */
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use x11rb::protocol::xproto::{EventMask, PropertyNotifyEvent, Window};
+use x11rb::rust_connection::RustConnection;
+
+use crate::data_collector::privacy;
+use crate::data_collector::window;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Name of the file holding the secret key of the application identifiers,
+/// relative to the state directory.
+const KEY_FILE: &str = "x11-sentinel-client/focus.key";
+
+/// Length of the secret key in bytes.
+const KEY_LENGTH: usize = 32;
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// The active window after a change.
+#[derive(Clone, Debug)]
+pub struct ActiveWindow {
+    /// X server timestamp of the change in milliseconds.
+    pub time: u64,
+    /// Identifier of the application, `None` if no window is active, the
+    /// application is unknown or excluded from the collection.
+    pub application: Option<String>,
+    /// Geometry of the window, `None` if no window is active.
+    pub geometry: Option<window::Geometry>,
+}
+
+/// Reports the changes of the active window.
+pub struct FocusTracker {
+    atoms: window::Atoms,
+    root: Window,
+    allowed_wm_classes: Vec<String>,
+    key: Vec<u8>,
+    active_window: Option<Window>,
+}
+
+impl FocusTracker {
+    /// Constructor for the `FocusTracker` object. Selects property change
+    /// events on the root window to track the active window. Class names
+    /// that are not allowed are hashed with the given key.
+    pub fn new(
+        connection: &RustConnection,
+        root: Window,
+        allowed_wm_classes: &str,
+        key: Vec<u8>,
+    ) -> FocusTracker {
+        let atoms = window::get_atoms(connection);
+        window::select_window_events(connection, root, EventMask::PROPERTY_CHANGE);
+        let active_window = window::get_active_window(connection, root, &atoms);
+        FocusTracker {
+            atoms,
+            root,
+            allowed_wm_classes: split_allowed_wm_classes(allowed_wm_classes),
+            key,
+            active_window,
+        }
+    }
+
+    /// Event handler for property change events. Returns the new active
+    /// window if it changed.
+    pub fn handle_event(
+        &mut self,
+        connection: &RustConnection,
+        event: &PropertyNotifyEvent,
+        privacy_filter: Option<&mut privacy::PrivacyFilter>,
+    ) -> Option<ActiveWindow> {
+        if event.window != self.root || event.atom != self.atoms._NET_ACTIVE_WINDOW {
+            return None;
+        }
+        let active_window = window::get_active_window(connection, self.root, &self.atoms);
+        if active_window == self.active_window {
+            return None;
+        }
+        self.active_window = active_window;
+
+        let active_window = match active_window {
+            Some(active_window) => active_window,
+            None => {
+                return Some(ActiveWindow {
+                    time: event.time.into(),
+                    application: None,
+                    geometry: None,
+                })
+            }
+        };
+        let excluded = match privacy_filter {
+            Some(privacy_filter) => privacy_filter.is_excluded(connection, active_window),
+            None => false,
+        };
+        let application = match excluded {
+            true => None,
+            false => self.get_application(connection, active_window),
+        };
+        return Some(ActiveWindow {
+            time: event.time.into(),
+            application,
+            geometry: window::get_geometry(connection, active_window, self.root),
+        });
+    }
+
+    /// Identifier of the application owning the window.
+    fn get_application(&self, connection: &RustConnection, window: Window) -> Option<String> {
+        // The class name is the last element of `WM_CLASS`.
+        let name = window::get_wm_class(connection, window).pop()?;
+        return Some(get_application_id(
+            name,
+            &self.allowed_wm_classes,
+            &self.key,
+        ));
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Load the secret key of the application identifiers from the user's state
+/// directory, `$XDG_STATE_HOME` or `~/.local/state`. A new random key is
+/// created on the first run.
+pub fn load_key() -> Result<Vec<u8>, String> {
+    let state_dir = match env::var_os("XDG_STATE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => match env::var_os("HOME") {
+            Some(home) => Path::new(&home).join(".local/state"),
+            None => return Err(String::from("neither XDG_STATE_HOME nor HOME is set")),
+        },
+    };
+    return load_key_from(&state_dir.join(KEY_FILE));
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Load the secret key from the file, creating the file with a new random key
+/// readable only by the user if it does not exist.
+fn load_key_from(path: &Path) -> Result<Vec<u8>, String> {
+    let error = |error: std::io::Error| format!("{}: {}", path.display(), error);
+    match fs::read(path) {
+        Ok(key) if key.len() == KEY_LENGTH => return Ok(key),
+        Ok(_key) => return Err(format!("{}: invalid key length", path.display())),
+        Err(err) if err.kind() == ErrorKind::NotFound => (),
+        Err(err) => return Err(error(err)),
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(parent)
+            .map_err(error)?;
+    }
+    let mut key = vec![0; KEY_LENGTH];
+    fs::File::open("/dev/urandom")
+        .and_then(|mut random| random.read_exact(&mut key))
+        .map_err(|err| format!("/dev/urandom: {}", err))?;
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(&key))
+        .map_err(error)?;
+    return Ok(key);
+}
+
+/// Split the comma separated list of allowed class names.
+fn split_allowed_wm_classes(allowed_wm_classes: &str) -> Vec<String> {
+    return allowed_wm_classes
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+}
+
+/// Identifier of the application with the given class name, the name itself
+/// if it is allowed, otherwise its HMAC-SHA256 keyed with the secret key.
+fn get_application_id(name: String, allowed_wm_classes: &[String], key: &[u8]) -> String {
+    if allowed_wm_classes.contains(&name.to_lowercase()) {
+        return name;
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+    mac.update(name.as_bytes());
+    return format!("{:x}", mac.finalize().into_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    const KEY: [u8; KEY_LENGTH] = [7; KEY_LENGTH];
+
+    #[test]
+    fn allowed_classes_are_reported_as_they_are() {
+        let allowed = split_allowed_wm_classes(" Firefox , xterm,,");
+        assert_eq!(allowed, vec!["firefox", "xterm"]);
+        assert_eq!(
+            get_application_id(String::from("Firefox"), &allowed, &KEY),
+            "Firefox"
+        );
+        assert_eq!(
+            get_application_id(String::from("XTerm"), &allowed, &KEY),
+            "XTerm"
+        );
+    }
+
+    #[test]
+    fn other_classes_are_keyed_hashes() {
+        let allowed = split_allowed_wm_classes("Firefox");
+        let id = get_application_id(String::from("KeePassXC"), &allowed, &KEY);
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&KEY).unwrap();
+        mac.update(b"KeePassXC");
+        assert_eq!(id, format!("{:x}", mac.finalize().into_bytes()));
+        assert_eq!(id.len(), 64);
+
+        // The identifier depends on the key and the class name.
+        let other_key = [8; KEY_LENGTH];
+        assert_ne!(
+            id,
+            get_application_id(String::from("KeePassXC"), &allowed, &other_key)
+        );
+        assert_ne!(
+            id,
+            get_application_id(String::from("keepassxc"), &allowed, &KEY)
+        );
+        assert_eq!(
+            id,
+            get_application_id(String::from("KeePassXC"), &allowed, &KEY)
+        );
+    }
+
+    #[test]
+    fn key_is_created_once_and_private() {
+        let dir = env::temp_dir().join(format!("x11-sentinel-focus-{}", std::process::id()));
+        let path = dir.join(KEY_FILE);
+        let _ = fs::remove_dir_all(&dir);
+
+        let key = load_key_from(&path).unwrap();
+        assert_eq!(key.len(), KEY_LENGTH);
+        assert_eq!(load_key_from(&path).unwrap(), key);
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let mode = fs::metadata(path.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        fs::write(&path, b"short").unwrap();
+        assert!(load_key_from(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
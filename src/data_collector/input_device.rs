@@ -0,0 +1,287 @@
+/**
+ * Module for parsing the input device list provided by the kernel in
+ * `/proc/bus/input/devices`.
+ *
+ * Every device is described by a block of lines separated by an empty line.
+ * Each line starts with a single letter identifying its content:
+ *
+ * I => @id: id of the device (bus type, vendor, product and version).
+ * N => name of the device.
+ * P => physical path to the device in the system hierarchy.
+ * S => sysfs path.
+ * U => unique identification code for the device (if device has it).
+ * H => list of input handles associated with the device.
+ * B => bitmaps, e.g. PROP (device properties and quirks), EV (types of events
+ *      supported by the device), KEY (keys/buttons this device has), MSC
+ *      (miscellaneous events supported by the device), LED (leds present on
+ *      the device).
+ */
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+const INPUT_DEVICES_PATH: &str = "/proc/bus/input/devices";
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputDevice {
+    bus: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+    name: String,
+    phys: String,
+    sysfs: String,
+    uniq: String,
+    handlers: Vec<String>,
+    capabilities: BTreeMap<String, String>,
+}
+
+impl InputDevice {
+    /// Whether a mouse handler is associated with the device.
+    pub fn is_mouse(&self) -> bool {
+        return self
+            .handlers
+            .iter()
+            .any(|handler| handler.starts_with("mouse"));
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Read and parse the input devices known by the kernel. An empty list is
+/// returned if the device list cannot be read.
+pub fn query_input_devices() -> Vec<InputDevice> {
+    match fs::read_to_string(INPUT_DEVICES_PATH) {
+        Ok(content) => return parse_input_devices(&content),
+        Err(error) => {
+            println!("Could not read {}: {}", INPUT_DEVICES_PATH, error);
+            return vec![];
+        }
+    }
+}
+
+/// Parse the content of the `/proc/bus/input/devices` file. Unknown or
+/// malformed lines are ignored.
+pub fn parse_input_devices(content: &str) -> Vec<InputDevice> {
+    let mut result = Vec::new();
+    let mut device: Option<InputDevice> = None;
+
+    for line in content.lines() {
+        let line = line.trim_end();
+
+        // An empty line closes the description of the current device.
+        if line.is_empty() {
+            if let Some(device) = device.take() {
+                result.push(device);
+            }
+            continue;
+        }
+
+        let (kind, value) = match line.split_once(": ") {
+            Some(result) => result,
+            None => continue,
+        };
+        let current = device.get_or_insert_with(InputDevice::default);
+
+        match kind {
+            "I" => parse_id(current, value),
+            "N" => current.name = parse_field(value, "Name"),
+            "P" => current.phys = parse_field(value, "Phys"),
+            "S" => current.sysfs = parse_field(value, "Sysfs"),
+            "U" => current.uniq = parse_field(value, "Uniq"),
+            "H" => {
+                current.handlers = parse_field(value, "Handlers")
+                    .split_whitespace()
+                    .map(String::from)
+                    .collect()
+            }
+            "B" => {
+                if let Some((name, bitmap)) = value.split_once('=') {
+                    current
+                        .capabilities
+                        .insert(String::from(name), String::from(bitmap));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // The last device is not necessarily followed by an empty line.
+    if let Some(device) = device {
+        result.push(device);
+    }
+
+    return result;
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Parse the `I` line, e.g. `Bus=0003 Vendor=046d Product=c077 Version=0111`.
+/// The values are hexadecimal numbers.
+fn parse_id(device: &mut InputDevice, value: &str) -> () {
+    for pair in value.split_whitespace() {
+        let (key, number) = match pair.split_once('=') {
+            Some(result) => result,
+            None => continue,
+        };
+        let number = match u16::from_str_radix(number, 16) {
+            Ok(number) => number,
+            Err(_error) => continue,
+        };
+        match key {
+            "Bus" => device.bus = number,
+            "Vendor" => device.vendor = number,
+            "Product" => device.product = number,
+            "Version" => device.version = number,
+            _ => (),
+        }
+    }
+}
+
+/// Parse a `Key=value` field and strip the surrounding quotes of the value.
+fn parse_field(value: &str, key: &str) -> String {
+    let value = match value.strip_prefix(key).and_then(|v| v.strip_prefix('=')) {
+        Some(value) => value,
+        None => value,
+    };
+    return String::from(value.trim_matches('"'));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUSE: &str = "\
+I: Bus=0003 Vendor=046d Product=c077 Version=0111
+N: Name=\"Logitech USB Optical Mouse\"
+P: Phys=usb-0000:00:14.0-2/input0
+S: Sysfs=/devices/pci0000:00/0000:00:14.0/usb1/1-2/1-2:1.0/0003:046D:C077.0001/input/input5
+U: Uniq=
+H: Handlers=mouse0 event5
+B: PROP=0
+B: EV=17
+B: KEY=70000 0 0 0 0
+B: REL=903
+B: MSC=10
+";
+
+    const TOUCHPAD: &str = "\
+I: Bus=0018 Vendor=06cb Product=cd8b Version=0100
+N: Name=\"SYNA3602:00 06CB:CD8B Touchpad\"
+P: Phys=i2c-SYNA3602:00
+S: Sysfs=/devices/platform/AMDI0010:03/i2c-0/i2c-SYNA3602:00/0018:06CB:CD8B.0002/input/input12
+U: Uniq=
+H: Handlers=event7 mouse1
+B: PROP=5
+B: EV=1b
+B: KEY=e520 10000 0 0 0 0
+B: ABS=2e0800000000003
+B: MSC=20
+";
+
+    const KEYBOARD: &str = "\
+I: Bus=0011 Vendor=0001 Product=0001 Version=ab83
+N: Name=\"AT Translated Set 2 keyboard\"
+P: Phys=isa0060/serio0/input0
+S: Sysfs=/devices/platform/i8042/serio0/input/input3
+U: Uniq=
+H: Handlers=sysrq kbd leds event3
+B: PROP=0
+B: EV=120013
+B: KEY=402000000 3803078f800d001 feffffdfffefffff fffffffffffffffe
+B: MSC=10
+B: LED=7
+";
+
+    #[test]
+    fn devices_are_parsed() {
+        let content = format!("{}\n{}\n{}\n", MOUSE, TOUCHPAD, KEYBOARD);
+        let devices = parse_input_devices(&content);
+        assert_eq!(devices.len(), 3);
+
+        let mouse = &devices[0];
+        assert_eq!(mouse.bus, 0x0003);
+        assert_eq!(mouse.vendor, 0x046d);
+        assert_eq!(mouse.product, 0xc077);
+        assert_eq!(mouse.version, 0x0111);
+        assert_eq!(mouse.name, "Logitech USB Optical Mouse");
+        assert_eq!(mouse.phys, "usb-0000:00:14.0-2/input0");
+        assert_eq!(mouse.uniq, "");
+        assert_eq!(mouse.handlers, vec!["mouse0", "event5"]);
+        assert_eq!(mouse.capabilities["REL"], "903");
+
+        assert_eq!(devices[1].name, "SYNA3602:00 06CB:CD8B Touchpad");
+        assert_eq!(devices[2].version, 0xab83);
+        assert_eq!(devices[2].handlers, vec!["sysrq", "kbd", "leds", "event3"]);
+    }
+
+    #[test]
+    fn mice_and_touchpads_are_mice() {
+        let content = format!("{}\n{}\n{}\n", MOUSE, TOUCHPAD, KEYBOARD);
+        let mice: Vec<bool> = parse_input_devices(&content)
+            .iter()
+            .map(InputDevice::is_mouse)
+            .collect();
+        assert_eq!(mice, vec![true, true, false]);
+    }
+
+    #[test]
+    fn last_device_without_trailing_empty_line_is_parsed() {
+        let content = format!("{}\n{}", MOUSE, KEYBOARD.trim_end());
+        let devices = parse_input_devices(&content);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[1].name, "AT Translated Set 2 keyboard");
+        assert_eq!(devices[1].capabilities["LED"], "7");
+    }
+
+    #[test]
+    fn multi_word_bitmaps_are_kept_whole() {
+        let devices = parse_input_devices(KEYBOARD);
+        assert_eq!(
+            devices[0].capabilities["KEY"],
+            "402000000 3803078f800d001 feffffdfffefffff fffffffffffffffe"
+        );
+        assert_eq!(devices[0].capabilities["EV"], "120013");
+    }
+
+    #[test]
+    fn malformed_lines_are_ignored() {
+        let content = "\
+I: Bus=zz Vendor=046d Product Version=123456
+N: Name=\"Broken\"
+B: garbage
+B:EV=17
+X
+H: Handlers=mouse2
+";
+        let devices = parse_input_devices(content);
+        assert_eq!(devices.len(), 1);
+        let device = &devices[0];
+        assert_eq!(device.bus, 0);
+        assert_eq!(device.vendor, 0x046d);
+        assert_eq!(device.product, 0);
+        assert_eq!(device.version, 0);
+        assert_eq!(device.name, "Broken");
+        assert!(device.capabilities.is_empty());
+        assert!(device.is_mouse());
+    }
+
+    #[test]
+    fn empty_content_has_no_devices() {
+        assert!(parse_input_devices("").is_empty());
+        assert!(parse_input_devices("\n\n").is_empty());
+    }
+}
@@ -4,30 +4,42 @@ use serde::Serialize;
  * Module for grouping platform and device specific metadata collection
  * functions.
  */
-use std::io::prelude::*;
-use std::process::Command;
-use std::process::Stdio;
+use std::env;
+use std::ffi::CStr;
+use std::fs;
+use std::path::Path;
 use timer::Timer;
 use x11rb::connection::Connection;
 use x11rb::protocol::randr::get_monitors;
+use x11rb::protocol::xinput;
 
+use crate::data_collector::input_device;
+use crate::data_collector::input_device::InputDevice;
 use crate::data_collector::utils;
 
+//==============================================================================
+// Constants
+//==============================================================================
+
+const MACHINE_ID_PATHS: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
 //==============================================================================
 // Structs
 //==============================================================================
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Metadata {
     user_name: String,
     host_id: String,
     monitor: Vec<MonitorMetadata>,
-    input_device: String,
+    input_device: Vec<InputDevice>,
     os: os_info::Info,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_change: Option<Vec<DeviceChangeMetadata>>,
 }
 
-#[derive(Copy, Clone, Debug, Serialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MonitorMetadata {
     name: u32,
@@ -41,6 +53,26 @@ pub struct MonitorMetadata {
     dpi: f64,
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceChangeMetadata {
+    device_id: u16,
+    attachment: u16,
+    device_type: String,
+    enabled: bool,
+    flags: Vec<String>,
+}
+
+impl Metadata {
+    /// Return a copy of the metadata that also describes the devices affected
+    /// by an input device hierarchy change.
+    pub fn with_device_change(&self, event: xinput::HierarchyEvent) -> Metadata {
+        let mut metadata = self.clone();
+        metadata.device_change = Some(get_device_change_metadata(event));
+        return metadata;
+    }
+}
+
 //==============================================================================
 // Public functions
 //==============================================================================
@@ -56,13 +88,13 @@ pub fn query_metadata() -> Metadata {
     let screen = &setup.roots[screen_number];
 
     // Currently logged on user.
-    let user_name = utils::get_env_var("USERNAME");
+    let user_name = get_user_name();
 
     // Unique identifier of the host machine.
     let host_id = get_host_id();
 
     // Get monitor metadata.
-    let monitor = get_monitor_metadata(&connection, &screen);
+    let monitor = get_monitor_metadata(&connection, screen);
 
     // Get input device metadata.
     let input_device = get_input_device_metadata();
@@ -76,6 +108,7 @@ pub fn query_metadata() -> Metadata {
         monitor,
         input_device,
         os,
+        device_change: None,
     }
 }
 
@@ -85,11 +118,8 @@ pub fn start_repeating_timer(
     query_interval: i64,
 ) -> (timer::Timer, timer::Guard) {
     let timer = Timer::new();
-    let guard = timer.schedule_repeating(Duration::milliseconds(query_interval), move || match tx
-        .send(utils::Message::MetadataChangedMessage)
-    {
-        Ok(_) => (),
-        Err(error) => println!("Could not send message: {}", error),
+    let guard = timer.schedule_repeating(Duration::milliseconds(query_interval), move || {
+        utils::send(&tx, utils::Message::MetadataChangedMessage);
     });
     return (timer, guard);
 }
@@ -112,6 +142,54 @@ fn mm_to_inch(x: f64) -> f64 {
 fn get_os_metadata() -> os_info::Info {
     os_info::get()
 }
+/// Collect the devices affected by a hierarchy change. Devices without any
+/// change flag set are skipped.
+fn get_device_change_metadata(event: xinput::HierarchyEvent) -> Vec<DeviceChangeMetadata> {
+    let mut result = Vec::new();
+    for info in event.infos {
+        if info.flags == 0 {
+            continue;
+        }
+        let device_type = match info.type_ {
+            xinput::DeviceType::MASTER_POINTER => "masterPointer",
+            xinput::DeviceType::MASTER_KEYBOARD => "masterKeyboard",
+            xinput::DeviceType::SLAVE_POINTER => "slavePointer",
+            xinput::DeviceType::SLAVE_KEYBOARD => "slaveKeyboard",
+            xinput::DeviceType::FLOATING_SLAVE => "floatingSlave",
+            _ => "unknown",
+        };
+        result.push(DeviceChangeMetadata {
+            device_id: info.deviceid,
+            attachment: info.attachment,
+            device_type: String::from(device_type),
+            enabled: info.enabled,
+            flags: get_hierarchy_flags(info.flags),
+        });
+    }
+    return result;
+}
+
+/// Convert the hierarchy change bitmask to a list of flag names.
+fn get_hierarchy_flags(flags: u32) -> Vec<String> {
+    let names = [
+        (xinput::HierarchyMask::MASTER_ADDED, "masterAdded"),
+        (xinput::HierarchyMask::MASTER_REMOVED, "masterRemoved"),
+        (xinput::HierarchyMask::SLAVE_ADDED, "slaveAdded"),
+        (xinput::HierarchyMask::SLAVE_REMOVED, "slaveRemoved"),
+        (xinput::HierarchyMask::SLAVE_ATTACHED, "slaveAttached"),
+        (xinput::HierarchyMask::SLAVE_DETACHED, "slaveDetached"),
+        (xinput::HierarchyMask::DEVICE_ENABLED, "deviceEnabled"),
+        (xinput::HierarchyMask::DEVICE_DISABLED, "deviceDisabled"),
+    ];
+    let mut result = Vec::new();
+    for (mask, name) in names {
+        if flags & u32::from(mask) != 0 {
+            result.push(String::from(name));
+        }
+    }
+    return result;
+}
+
 /// Query information about the monitors which are being used.
 /// The `MonitorInfo` struct contains various information about the monitors
 /// including the pixel dimensions, physical dimensions, layout and more.
@@ -155,80 +233,95 @@ fn get_monitor_metadata(
 }
 
 /// Query information about input devices with mouse capabilities.
-/// The function reads the `/proc/bus/input/devices` file and uses the `grep`
-/// command to filter mouse devices.
-///
-/// The result of the function can be interpreted as follows:
-///
-/// The B in front stands for bitmap, N, P, S, U, H are simply first letter in
-/// corresponding name value and I is for ID. In ordered fashion:
-///
-/// I => @id: id of the device
-/// Bus     => id.bustype
-/// Vendor  => id.vendor
-/// Product => id.product
-/// Version => id.version
-/// N => name of the device.
-/// P => physical path to the device in the system hierarchy.
-/// S => sysfs path.
-/// U => unique identification code for the device (if device has it).
-/// H => list of input handles associated with the device.
-/// B => bitmaps
-/// PROP => device properties and quirks.
-/// EV   => types of events supported by the device.
-/// KEY  => keys/buttons this device has.
-/// MSC  => miscellaneous events supported by the device.
-/// LED  => leds present on the device.
-fn get_input_device_metadata() -> String {
-    let process1 = match Command::new("cat")
-        .arg("/proc/bus/input/devices")
-        .stdout(Stdio::piped())
-        .spawn()
-    {
-        Ok(process) => process,
-        Err(error) => {
-            println!("Could not spawn cat: {}", error);
-            return String::new();
-        }
-    };
+/// The function parses the `/proc/bus/input/devices` file and keeps the devices
+/// that have a mouse handler associated with them.
+fn get_input_device_metadata() -> Vec<InputDevice> {
+    return input_device::query_input_devices()
+        .into_iter()
+        .filter(|device| device.is_mouse())
+        .collect();
+}
 
-    let process2 = match Command::new("grep")
-        .args(["-B", "5", "-A", "5", "mouse"])
-        .stdin(process1.stdout.unwrap())
-        .stdout(Stdio::piped())
-        .spawn()
-    {
-        Ok(process) => process,
-        Err(error) => {
-            println!("Could not spawn grep: {}", error);
-            return String::new();
-        }
-    };
+/// Get the unique identifier of the host machine by reading the
+/// `/etc/machine-id` file, falling back to `/var/lib/dbus/machine-id`. An empty
+/// string is returned if neither of them holds an identifier.
+fn get_host_id() -> String {
+    return read_host_id(&MACHINE_ID_PATHS);
+}
 
-    let mut s = String::new();
-    match process2.stdout.unwrap().read_to_string(&mut s) {
-        Ok(_) => return s,
-        Err(error) => {
-            println!("couldn't read grep stdout: {}", error);
-            return String::new();
+/// Read the host identifier from the first of the files holding one. Empty
+/// files, e.g. the `/etc/machine-id` of an image not booted yet, are skipped.
+fn read_host_id<P: AsRef<Path>>(paths: &[P]) -> String {
+    for path in paths {
+        let path = path.as_ref();
+        match fs::read_to_string(path) {
+            Ok(value) if !value.trim().is_empty() => return String::from(value.trim()),
+            Ok(_value) => println!("Skipping empty {}", path.display()),
+            Err(error) => println!("Could not read {}: {}", path.display(), error),
         }
     }
+    return String::new();
 }
 
-/// Get the unique identifier of the host machine by reading the
-/// `/etc/machine-id` file.
-fn get_host_id() -> String {
-    match Command::new("cat").arg("/etc/machine-id").output() {
-        Ok(output) => match String::from_utf8(output.stdout) {
-            Ok(value) => return String::from(value.trim()),
-            Err(error) => {
-                println!("Could not parse output: {}", error);
-                return String::new();
-            }
-        },
-        Err(error) => {
-            println!("Could not spawn cat: {}", error);
-            return String::new();
-        }
+/// Get the name of the user running the application from the passwd database.
+/// The `USER` environment variable is used if the lookup fails.
+fn get_user_name() -> String {
+    let uid = unsafe { libc::getuid() };
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+    let mut buffer = vec![0 as libc::c_char; 4096];
+
+    let code = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut passwd,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
     };
+
+    if code == 0 && !result.is_null() {
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) };
+        return name.to_string_lossy().into_owned();
+    }
+
+    println!("Could not find passwd entry of uid {}", uid);
+    return env::var("USER").unwrap_or_default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+    use std::process;
+
+    fn write_file(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        return path;
+    }
+
+    #[test]
+    fn host_id_is_trimmed_and_empty_files_are_skipped() {
+        let dir = env::temp_dir().join(format!("x11-sentinel-metadata-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let empty = write_file(&dir, "empty", "\n");
+        let first = write_file(&dir, "first", "0123456789abcdef0123456789abcdef\n");
+        let second = write_file(&dir, "second", " fedcba9876543210fedcba9876543210 ");
+        let missing = dir.join("missing");
+
+        assert_eq!(
+            read_host_id(&[&first, &second]),
+            "0123456789abcdef0123456789abcdef"
+        );
+        assert_eq!(
+            read_host_id(&[&missing, &empty, &second]),
+            "fedcba9876543210fedcba9876543210"
+        );
+        assert_eq!(read_host_id(&[&missing, &empty]), "");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
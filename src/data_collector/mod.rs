@@ -1,19 +1,34 @@
 /**
  * This module implements the main data collection logic.
  */
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::mpsc;
+use std::sync::OnceLock;
 use std::thread;
+use std::time::{Duration, Instant};
 
 use serde::Serialize;
 use serde_json::json;
 
 use x11rb::connection::Connection;
+use x11rb::protocol::screensaver;
 use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
 
 use crate::config;
+use crate::dbus;
+use crate::health;
+use crate::metrics;
+use crate::session;
+use crate::systemd;
 
+mod focus;
+mod input_device;
 mod metadata;
+mod pointer;
+mod privacy;
 mod utils;
+mod window;
 
 //==============================================================================
 // Constants
@@ -27,22 +42,74 @@ const TOUCH_END_EVENT_TYPE: u8 = 4;
 const BUTTON_PRESS_EVENT_TYPE: u8 = 5;
 const BUTTON_RELEASE_EVENT_TYPE: u8 = 6;
 const METADATA_CHANGED_EVENT_TYPE: u8 = 7;
+const SESSION_LOCKED_EVENT_TYPE: u8 = 8;
+const SESSION_UNLOCKED_EVENT_TYPE: u8 = 9;
+const SUSPEND_EVENT_TYPE: u8 = 10;
+const RESUME_EVENT_TYPE: u8 = 11;
+const IDLE_EVENT_TYPE: u8 = 12;
+const ACTIVE_EVENT_TYPE: u8 = 13;
+const GAP_EVENT_TYPE: u8 = 14;
+const ACTIVE_WINDOW_CHANGED_EVENT_TYPE: u8 = 15;
+
+/// Number of seconds to wait for the main event loop to report its state.
+const QUERY_STATE_TIMEOUT: u64 = 5;
+
+/// Number of seconds the system may be kept from going to sleep until the
+/// buffer is submitted. Matches the default `InhibitDelayMaxSec` of logind.
+const SUSPEND_SUBMIT_TIMEOUT: u64 = 5;
+
+/// Number of milliseconds without further metadata change notifications
+/// before the metadata is queried. A single monitor configuration change is
+/// announced by a burst of RandR events.
+const METADATA_CHANGED_DELAY: u64 = 300;
+
+//==============================================================================
+// Statics
+//==============================================================================
+
+/// Whether input event collection is paused.
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Upper limit for the event buffer's size. When the event buffer's size
+/// reaches this number it triggers a submission.
+static BUFFER_SIZE_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// If no new event is generated for this number of milliseconds, a submission
+/// gets triggered.
+static IDLE_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+
+/// Sender of the main event loop's channel.
+static MESSAGE_TX: OnceLock<mpsc::Sender<utils::Message>> = OnceLock::new();
 
 //==============================================================================
 // Structs
 //==============================================================================
 
+/// State of the data collection reported to the user.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectorState {
+    pub paused: bool,
+    pub buffer_size: usize,
+    pub sequence_number: u64,
+}
+
+/// Pointer coordinates relative to the window under the pointer, followed by
+/// the width and the height of the window.
+#[derive(Clone, Debug, Serialize)]
+struct PointerWindow(i16, i16, u16, u16);
+
 struct State {
     buffer: Vec<EventType>,
-    buffer_size_limit: usize,
     api_key_name: String,
     api_key_value: String,
     submit_url: String,
     epoch: u64,
-    session_id: String,
+    session: session::Session,
     stream_id: String,
     sequence_number: u64,
     user_id: String,
+    metadata: Option<metadata::Metadata>,
 }
 
 impl State {
@@ -51,10 +118,6 @@ impl State {
         // Initialize empty buffer
         let buffer = vec![];
 
-        // Upper limit for the event buffer's size. When the event buffer's size
-        // reaches this number it triggers a submission.
-        let buffer_size_limit: usize = config.buffer_size_limit.unwrap();
-
         // Name of the API key that is sent with every submission request.
         let api_key_name: String = config.api_key_name.unwrap();
 
@@ -67,8 +130,8 @@ impl State {
         // Milliseconds since 00:00:00 UTC 1 January 1970
         let epoch = utils::now();
 
-        // Unique identifier of the current user session.
-        let session_id = utils::get_session_id();
+        // Identity of the current user session.
+        let session = session::query_session();
 
         // Sequence number for chunk submissions starting at 0.
         let sequence_number = 0;
@@ -76,17 +139,20 @@ impl State {
         // User ID
         let user_id = config.user_id.unwrap();
 
+        // Last pushed metadata, used to skip identical metadata.
+        let metadata = None;
+
         State {
             buffer,
-            buffer_size_limit,
             api_key_name,
             api_key_value,
             submit_url,
             epoch,
-            session_id,
+            session,
             stream_id,
             sequence_number,
             user_id,
+            metadata,
         }
     }
 
@@ -94,7 +160,8 @@ impl State {
     fn handle_raw_motion_event(
         &mut self,
         event: x11rb::protocol::xinput::RawMotionEvent,
-        pointer: x11rb::protocol::xproto::QueryPointerReply,
+        pointer: pointer::Pointer,
+        window: Option<window::Geometry>,
     ) -> () {
         self.push(EventType::MotionEvent(
             MOTION_EVENT_TYPE,
@@ -105,6 +172,7 @@ impl State {
             event.axisvalues_raw[1].frac,
             pointer.root_x,
             pointer.root_y,
+            get_pointer_window(&pointer, window),
         ));
     }
 
@@ -112,7 +180,8 @@ impl State {
     fn handle_scroll_event(
         &mut self,
         event: x11rb::protocol::xinput::RawMotionEvent,
-        pointer: x11rb::protocol::xproto::QueryPointerReply,
+        pointer: pointer::Pointer,
+        window: Option<window::Geometry>,
     ) -> () {
         self.push(EventType::ScrollEvent(
             SCROLL_EVENT_TYPE,
@@ -121,6 +190,7 @@ impl State {
             event.axisvalues_raw[0].frac,
             pointer.root_x,
             pointer.root_y,
+            get_pointer_window(&pointer, window),
         ));
     }
 
@@ -128,7 +198,8 @@ impl State {
     fn handle_touch_begin_event(
         &mut self,
         event: x11rb::protocol::xinput::RawTouchBeginEvent,
-        pointer: x11rb::protocol::xproto::QueryPointerReply,
+        pointer: pointer::Pointer,
+        window: Option<window::Geometry>,
     ) -> () {
         self.push(EventType::TouchBeginEvent(
             TOUCH_BEGIN_EVENT_TYPE,
@@ -139,6 +210,7 @@ impl State {
             event.axisvalues_raw[1].frac,
             pointer.root_x,
             pointer.root_y,
+            get_pointer_window(&pointer, window),
         ));
     }
 
@@ -146,7 +218,8 @@ impl State {
     fn handle_touch_update_event(
         &mut self,
         event: x11rb::protocol::xinput::RawTouchUpdateEvent,
-        pointer: x11rb::protocol::xproto::QueryPointerReply,
+        pointer: pointer::Pointer,
+        window: Option<window::Geometry>,
     ) -> () {
         self.push(EventType::TouchUpdateEvent(
             TOUCH_UPDATE_EVENT_TYPE,
@@ -157,6 +230,7 @@ impl State {
             event.axisvalues_raw[1].frac,
             pointer.root_x,
             pointer.root_y,
+            get_pointer_window(&pointer, window),
         ));
     }
 
@@ -164,7 +238,8 @@ impl State {
     fn handle_touch_end_event(
         &mut self,
         event: x11rb::protocol::xinput::RawTouchEndEvent,
-        pointer: x11rb::protocol::xproto::QueryPointerReply,
+        pointer: pointer::Pointer,
+        window: Option<window::Geometry>,
     ) -> () {
         self.push(EventType::TouchEndEvent(
             TOUCH_END_EVENT_TYPE,
@@ -175,6 +250,7 @@ impl State {
             event.axisvalues_raw[1].frac,
             pointer.root_x,
             pointer.root_y,
+            get_pointer_window(&pointer, window),
         ));
     }
 
@@ -182,7 +258,8 @@ impl State {
     fn handle_button_press_event(
         &mut self,
         event: x11rb::protocol::xinput::RawButtonPressEvent,
-        pointer: x11rb::protocol::xproto::QueryPointerReply,
+        pointer: pointer::Pointer,
+        window: Option<window::Geometry>,
     ) -> () {
         self.push(EventType::ButtonPressEvent(
             BUTTON_PRESS_EVENT_TYPE,
@@ -190,6 +267,7 @@ impl State {
             pointer.root_x,
             pointer.root_y,
             event.detail,
+            get_pointer_window(&pointer, window),
         ));
     }
 
@@ -197,7 +275,8 @@ impl State {
     fn handle_button_release_event(
         &mut self,
         event: x11rb::protocol::xinput::RawButtonReleaseEvent,
-        pointer: x11rb::protocol::xproto::QueryPointerReply,
+        pointer: pointer::Pointer,
+        window: Option<window::Geometry>,
     ) -> () {
         self.push(EventType::ButtonReleaseEvent(
             BUTTON_RELEASE_EVENT_TYPE,
@@ -205,12 +284,18 @@ impl State {
             pointer.root_x,
             pointer.root_y,
             event.detail,
+            get_pointer_window(&pointer, window),
         ));
     }
 
-    /// Event handler for `MetadataChangedEvent`.
+    /// Event handler for `MetadataChangedEvent`. Metadata identical to the
+    /// last pushed one is not pushed again.
     fn handle_metadata_changed_event(&mut self) -> () {
         let metadata = metadata::query_metadata();
+        if self.metadata.as_ref() == Some(&metadata) {
+            return;
+        }
+        self.metadata = Some(metadata.clone());
         self.push(EventType::MetadataChangedEvent(
             METADATA_CHANGED_EVENT_TYPE,
             utils::now(),
@@ -218,13 +303,85 @@ impl State {
         ));
     }
 
+    /// Event handler for `HierarchyEvent`. Emits a `MetadataChangedEvent`
+    /// describing the added, removed or reattached input devices.
+    fn handle_hierarchy_event(&mut self, event: x11rb::protocol::xinput::HierarchyEvent) -> () {
+        let metadata = metadata::query_metadata();
+        self.metadata = Some(metadata.clone());
+        self.push(EventType::MetadataChangedEvent(
+            METADATA_CHANGED_EVENT_TYPE,
+            utils::now(),
+            metadata.with_device_change(event),
+        ));
+    }
+
+    /// Event handler for session lifecycle events. The buffer is submitted
+    /// before the system goes to sleep.
+    fn handle_session_event(&mut self, event: session::SessionEvent) -> () {
+        let time = utils::now();
+        self.push(match event {
+            session::SessionEvent::Locked => {
+                EventType::SessionLockedEvent(SESSION_LOCKED_EVENT_TYPE, time)
+            }
+            session::SessionEvent::Unlocked => {
+                EventType::SessionUnlockedEvent(SESSION_UNLOCKED_EVENT_TYPE, time)
+            }
+            session::SessionEvent::Suspend => EventType::SuspendEvent(SUSPEND_EVENT_TYPE, time),
+            session::SessionEvent::Resume => EventType::ResumeEvent(RESUME_EVENT_TYPE, time),
+            session::SessionEvent::Idle => EventType::IdleEvent(IDLE_EVENT_TYPE, time),
+            session::SessionEvent::Active => EventType::ActiveEvent(ACTIVE_EVENT_TYPE, time),
+        });
+
+        // Do not keep events in the buffer while the system is asleep.
+        if event == session::SessionEvent::Suspend {
+            self.submit();
+        }
+    }
+
+    /// Event handler for `GapEvent`, marking the time span in which events
+    /// were dropped because an excluded window was in use.
+    fn handle_gap_event(&mut self, start: u64, end: u64) -> () {
+        self.push(EventType::GapEvent(GAP_EVENT_TYPE, start, end));
+    }
+
+    /// Event handler for `ActiveWindowChangedEvent`. The geometry is zero if
+    /// no window is active.
+    fn handle_active_window_changed_event(&mut self, active_window: focus::ActiveWindow) -> () {
+        let geometry = active_window.geometry.unwrap_or(window::Geometry {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        });
+        self.push(EventType::ActiveWindowChangedEvent(
+            ACTIVE_WINDOW_CHANGED_EVENT_TYPE,
+            active_window.time,
+            active_window.application,
+            geometry.x,
+            geometry.y,
+            geometry.width,
+            geometry.height,
+        ));
+    }
+
+    /// Return the state of the data collection.
+    fn get_state(&self) -> CollectorState {
+        CollectorState {
+            paused: is_paused(),
+            buffer_size: self.buffer.len(),
+            sequence_number: self.sequence_number,
+        }
+    }
+
     fn increment_sequence_number(&mut self) -> () {
-        self.sequence_number = self.sequence_number + 1;
+        self.sequence_number += 1;
     }
 
     fn push(&mut self, event: EventType) -> () {
+        metrics::EVENTS.with_label_values(&[event.name()]).inc();
+        health::record_event();
         self.buffer.push(event);
-        if self.buffer.len() > self.buffer_size_limit {
+        if self.buffer.len() > BUFFER_SIZE_LIMIT.load(Ordering::SeqCst) {
             self.submit();
         }
     }
@@ -243,7 +400,7 @@ impl State {
         let send_buffer = self.flush_buffer();
 
         // Do not send empty buffer
-        if send_buffer.len() == 0 {
+        if send_buffer.is_empty() {
             return;
         }
 
@@ -251,7 +408,8 @@ impl State {
         let body = json!({
             "metadata": {
                 "epoch": { "unit": "millisecond", "value": self.epoch },
-                "sessionId": self.session_id,
+                "sessionId": self.session.id,
+                "session": self.session,
                 "streamId": self.stream_id,
                 "sequenceNumber": self.sequence_number,
                 "userId": self.user_id
@@ -259,15 +417,41 @@ impl State {
             "chunk": send_buffer,
         });
 
+        let body = match serde_json::to_vec(&body) {
+            Ok(body) => body,
+            Err(error) => {
+                println!("Could not serialize chunk: {}", error);
+                return;
+            }
+        };
+        let body_size = body.len();
+
         let client = reqwest::Client::new();
 
         // Send the request
-        let _result = client
+        let timer = metrics::SUBMIT_DURATION.start_timer();
+        let result = client
             .post(&self.submit_url)
-            .json(&body)
+            .body(body)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
             .header(&self.api_key_name, &self.api_key_value)
             .send()
-            .await;
+            .await
+            .and_then(|response| response.error_for_status());
+        timer.observe_duration();
+
+        match result {
+            Ok(_response) => {
+                metrics::CHUNKS_SENT.inc();
+                health::record_submit();
+                metrics::BYTES_UPLOADED.inc_by(body_size as u64);
+            }
+            Err(error) => {
+                println!("Could not submit chunk: {}", error);
+                metrics::CHUNKS_FAILED.inc();
+                dbus::emit_submit_failed(self.sequence_number, &error.to_string());
+            }
+        }
 
         self.increment_sequence_number();
     }
@@ -277,79 +461,388 @@ impl State {
 // Enums
 //==============================================================================
 
+// The variants are named after the events of the schema.
+#[allow(clippy::enum_variant_names)]
 #[derive(Clone, Debug, Serialize)]
 #[serde(untagged)]
 enum EventType {
-    MotionEvent(u8, u64, i32, u32, i32, u32, i16, i16),
-    ScrollEvent(u8, u64, i32, u32, i16, i16),
-    TouchBeginEvent(u8, u64, i32, u32, i32, u32, i16, i16),
-    TouchUpdateEvent(u8, u64, i32, u32, i32, u32, i16, i16),
-    TouchEndEvent(u8, u64, i32, u32, i32, u32, i16, i16),
-    ButtonPressEvent(u8, u64, i16, i16, u32),
-    ButtonReleaseEvent(u8, u64, i16, i16, u32),
+    MotionEvent(
+        u8,
+        u64,
+        i32,
+        u32,
+        i32,
+        u32,
+        i16,
+        i16,
+        #[serde(skip_serializing_if = "Option::is_none")] Option<PointerWindow>,
+    ),
+    ScrollEvent(
+        u8,
+        u64,
+        i32,
+        u32,
+        i16,
+        i16,
+        #[serde(skip_serializing_if = "Option::is_none")] Option<PointerWindow>,
+    ),
+    TouchBeginEvent(
+        u8,
+        u64,
+        i32,
+        u32,
+        i32,
+        u32,
+        i16,
+        i16,
+        #[serde(skip_serializing_if = "Option::is_none")] Option<PointerWindow>,
+    ),
+    TouchUpdateEvent(
+        u8,
+        u64,
+        i32,
+        u32,
+        i32,
+        u32,
+        i16,
+        i16,
+        #[serde(skip_serializing_if = "Option::is_none")] Option<PointerWindow>,
+    ),
+    TouchEndEvent(
+        u8,
+        u64,
+        i32,
+        u32,
+        i32,
+        u32,
+        i16,
+        i16,
+        #[serde(skip_serializing_if = "Option::is_none")] Option<PointerWindow>,
+    ),
+    ButtonPressEvent(
+        u8,
+        u64,
+        i16,
+        i16,
+        u32,
+        #[serde(skip_serializing_if = "Option::is_none")] Option<PointerWindow>,
+    ),
+    ButtonReleaseEvent(
+        u8,
+        u64,
+        i16,
+        i16,
+        u32,
+        #[serde(skip_serializing_if = "Option::is_none")] Option<PointerWindow>,
+    ),
     MetadataChangedEvent(u8, u64, metadata::Metadata),
+    SessionLockedEvent(u8, u64),
+    SessionUnlockedEvent(u8, u64),
+    SuspendEvent(u8, u64),
+    ResumeEvent(u8, u64),
+    IdleEvent(u8, u64),
+    ActiveEvent(u8, u64),
+    GapEvent(u8, u64, u64),
+    ActiveWindowChangedEvent(u8, u64, Option<String>, i16, i16, u16, u16),
+}
+
+impl EventType {
+    /// Name of the event type, used as metrics label.
+    fn name(&self) -> &'static str {
+        match self {
+            EventType::MotionEvent(..) => "motion",
+            EventType::ScrollEvent(..) => "scroll",
+            EventType::TouchBeginEvent(..) => "touch_begin",
+            EventType::TouchUpdateEvent(..) => "touch_update",
+            EventType::TouchEndEvent(..) => "touch_end",
+            EventType::ButtonPressEvent(..) => "button_press",
+            EventType::ButtonReleaseEvent(..) => "button_release",
+            EventType::MetadataChangedEvent(..) => "metadata_changed",
+            EventType::SessionLockedEvent(..) => "session_locked",
+            EventType::SessionUnlockedEvent(..) => "session_unlocked",
+            EventType::SuspendEvent(..) => "suspend",
+            EventType::ResumeEvent(..) => "resume",
+            EventType::IdleEvent(..) => "idle",
+            EventType::ActiveEvent(..) => "active",
+            EventType::GapEvent(..) => "gap",
+            EventType::ActiveWindowChangedEvent(..) => "active_window_changed",
+        }
+    }
 }
 
 //==============================================================================
 // Public functions
 //==============================================================================
 
+/// Pause the collection of input events.
+pub fn pause() -> () {
+    PAUSED.store(true, Ordering::SeqCst);
+}
+
+/// Resume the collection of input events.
+pub fn resume() -> () {
+    PAUSED.store(false, Ordering::SeqCst);
+}
+
+/// Whether the collection of input events is paused.
+pub fn is_paused() -> bool {
+    return PAUSED.load(Ordering::SeqCst);
+}
+
+/// Submit the content of the event buffer right away.
+pub fn flush() -> Result<(), String> {
+    let tx = MESSAGE_TX
+        .get()
+        .ok_or("the data collector is not running")?;
+    utils::send(tx, utils::Message::FlushMessage);
+    return Ok(());
+}
+
+/// Query the platform and device specific metadata right away.
+pub fn refresh_metadata() -> Result<(), String> {
+    let tx = MESSAGE_TX
+        .get()
+        .ok_or("the data collector is not running")?;
+    utils::send(tx, utils::Message::MetadataChangedMessage);
+    return Ok(());
+}
+
+/// Query the state of the data collection from the main event loop.
+pub fn query_state() -> Result<CollectorState, String> {
+    let tx = MESSAGE_TX
+        .get()
+        .ok_or("the data collector is not running")?;
+    let (state_tx, state_rx) = mpsc::channel();
+    utils::send(tx, utils::Message::QueryStateMessage(state_tx));
+    return state_rx
+        .recv_timeout(Duration::from_secs(QUERY_STATE_TIMEOUT))
+        .map_err(|error| error.to_string());
+}
+
+/// Check whether the applications and windows excluded from the collection
+/// are valid, so that invalid settings are reported at startup.
+pub fn check_exclusion(config: &config::Config) -> Result<(), String> {
+    return get_exclusion(config).map(|_exclusion| ());
+}
+
+/// Serializes the tests sending messages to the main event loop, as they
+/// share the `CHANNEL_BACKLOG` gauge.
+#[cfg(test)]
+pub static TEST_CHANNEL_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Answer the state queries in place of the main event loop, for the tests of
+/// the modules controlling the data collector.
+#[cfg(test)]
+pub fn serve_test_state(buffer_size: usize, sequence_number: u64) -> () {
+    let (tx, rx) = mpsc::channel();
+    if MESSAGE_TX.set(tx).is_err() {
+        return;
+    }
+    thread::spawn(move || {
+        for message in rx {
+            metrics::CHANNEL_BACKLOG.dec();
+            if let utils::Message::QueryStateMessage(state_tx) = message {
+                let _ = state_tx.send(CollectorState {
+                    paused: is_paused(),
+                    buffer_size,
+                    sequence_number,
+                });
+            }
+        }
+    });
+}
+
+/// Change the upper limit for the event buffer's size while collecting.
+pub fn set_buffer_size_limit(buffer_size_limit: usize) -> () {
+    BUFFER_SIZE_LIMIT.store(buffer_size_limit, Ordering::SeqCst);
+}
+
+/// Change the idle timeout in milliseconds while collecting.
+pub fn set_idle_timeout(idle_timeout: u64) -> () {
+    IDLE_TIMEOUT.store(idle_timeout, Ordering::SeqCst);
+}
+
 pub fn run(config: config::Config, stream_id: String) -> () {
-    let idle_timeout = config.idle_timeout.unwrap();
     let metadata_query_interval = config.metadata_query_interval.unwrap();
+    let collect_config = config.clone();
     let mut state = State::new(config, stream_id);
 
     // Collect platform and device specific metadata.
     state.handle_metadata_changed_event();
 
-    // Create and start a repeating timer for querying metadata.
+    // Create and start a repeating timer for querying metadata. Metadata
+    // changes are reported by X events as well, the timer is only a safety
+    // net for changes that are not announced.
     let (tx, rx) = mpsc::channel();
+    let _ = MESSAGE_TX.set(tx.clone());
     let (_timer, _guard) = metadata::start_repeating_timer(tx.clone(), metadata_query_interval);
 
+    // Watch session lock and sleep state changes.
+    let session_tx = tx.clone();
+    session::watch_session(move |event| {
+        if event != session::SessionEvent::Suspend {
+            utils::send(
+                &session_tx,
+                utils::Message::SessionEventMessage(event, None),
+            );
+            return;
+        }
+        // Keep the system awake until the buffer is submitted.
+        let (done_tx, done_rx) = mpsc::channel();
+        utils::send(
+            &session_tx,
+            utils::Message::SessionEventMessage(event, Some(done_tx)),
+        );
+        match done_rx.recv_timeout(Duration::from_secs(SUSPEND_SUBMIT_TIMEOUT)) {
+            Ok(()) => (),
+            Err(error) => println!("Could not submit events before sleep: {}", error),
+        }
+    });
+
     // Start the status polling service
     thread::spawn(move || {
-        collect(tx.clone());
+        collect(tx.clone(), collect_config);
     });
 
-    // Main event loop.
+    // Main event loop. The loop wakes up at least once per watchdog interval,
+    // so that systemd can tell whether it makes progress.
+    let _liveness = health::start(health::Subsystem::Submitter);
+    let mut watchdog = systemd::Watchdog::new();
+    let mut last_message = Instant::now();
+    let metadata_changed_delay = Duration::from_millis(METADATA_CHANGED_DELAY);
+    let mut metadata_changed: Option<Instant> = None;
     loop {
-        match rx.recv_timeout(std::time::Duration::from_millis(idle_timeout)) {
-            Ok(msg) => match msg {
-                utils::Message::MetadataChangedMessage => state.handle_metadata_changed_event(),
-                utils::Message::X11EventMessage(event, pointer) => {
-                    // Handle motion events.
-                    match event {
-                        Event::XinputRawMotion(event) => match event.axisvalues_raw.len() {
-                            1 => state.handle_scroll_event(event, pointer),
-                            2 => state.handle_raw_motion_event(event, pointer),
-                            _ => (),
-                        },
-                        Event::XinputRawTouchBegin(event) => {
-                            state.handle_touch_begin_event(event, pointer);
+        watchdog.ping();
+
+        let idle_timeout = Duration::from_millis(IDLE_TIMEOUT.load(Ordering::SeqCst));
+        let mut wait = idle_timeout.saturating_sub(last_message.elapsed());
+        if let Some(interval) = watchdog.interval() {
+            wait = wait.min(interval);
+        }
+        if let Some(changed) = metadata_changed {
+            wait = wait.min(metadata_changed_delay.saturating_sub(changed.elapsed()));
+        }
+
+        match utils::receive(&rx, wait) {
+            Ok(msg) => {
+                last_message = Instant::now();
+                match msg {
+                    utils::Message::FlushMessage => state.submit(),
+                    // Query the metadata once the notifications stop.
+                    utils::Message::MetadataChangedMessage => {
+                        metadata_changed = Some(Instant::now());
+                    }
+                    utils::Message::QueryStateMessage(state_tx) => {
+                        match state_tx.send(state.get_state()) {
+                            Ok(()) => (),
+                            Err(error) => println!("Could not send state: {}", error),
                         }
-                        Event::XinputRawTouchUpdate(event) => {
-                            state.handle_touch_update_event(event, pointer);
+                    }
+                    utils::Message::SessionEventMessage(event, done_tx) => {
+                        state.handle_session_event(event);
+                        if let Some(done_tx) = done_tx {
+                            let _ = done_tx.send(());
                         }
-                        Event::XinputRawTouchEnd(event) => {
-                            state.handle_touch_end_event(event, pointer);
+                    }
+                    utils::Message::ActiveWindowMessage(active_window) => {
+                        if !is_paused() {
+                            state.handle_active_window_changed_event(active_window);
                         }
-                        Event::XinputRawButtonPress(event) => {
-                            state.handle_button_press_event(event, pointer);
+                    }
+                    utils::Message::PrivacyGapMessage(start, end) => {
+                        // The gap is of no interest while the collection is
+                        // paused anyway.
+                        if !is_paused() {
+                            state.handle_gap_event(start, end);
                         }
-                        Event::XinputRawButtonRelease(event) => {
-                            state.handle_button_release_event(event, pointer);
+                    }
+                    utils::Message::HierarchyMessage(event) => {
+                        state.handle_hierarchy_event(event);
+                    }
+                    utils::Message::X11EventMessage(event, pointer, window) => {
+                        // Handle motion events.
+                        match event {
+                            // Drop input events while the collection is paused.
+                            _ if is_paused() => (),
+                            Event::XinputRawMotion(event) => match event.axisvalues_raw.len() {
+                                1 => state.handle_scroll_event(event, pointer, window),
+                                2 => state.handle_raw_motion_event(event, pointer, window),
+                                _ => (),
+                            },
+                            Event::XinputRawTouchBegin(event) => {
+                                state.handle_touch_begin_event(event, pointer, window);
+                            }
+                            Event::XinputRawTouchUpdate(event) => {
+                                state.handle_touch_update_event(event, pointer, window);
+                            }
+                            Event::XinputRawTouchEnd(event) => {
+                                state.handle_touch_end_event(event, pointer, window);
+                            }
+                            Event::XinputRawButtonPress(event) => {
+                                state.handle_button_press_event(event, pointer, window);
+                            }
+                            Event::XinputRawButtonRelease(event) => {
+                                state.handle_button_release_event(event, pointer, window);
+                            }
+                            _ => (),
                         }
-                        _ => (),
                     }
                 }
-            },
-            Err(mpsc::RecvTimeoutError::Timeout) => state.submit(),
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if last_message.elapsed() >= idle_timeout {
+                    state.submit();
+                    last_message = Instant::now();
+                }
+            }
             Err(_) => continue,
         }
+
+        if let Some(changed) = metadata_changed {
+            if changed.elapsed() >= metadata_changed_delay {
+                metadata_changed = None;
+                state.handle_metadata_changed_event();
+            }
+        }
     }
 }
 
-fn collect(tx: std::sync::mpsc::Sender<utils::Message>) -> () {
+/// Pointer coordinates relative to the window under the pointer, `None` if
+/// the pointer is not over a window or the coordinates are not reported.
+fn get_pointer_window(
+    pointer: &pointer::Pointer,
+    window: Option<window::Geometry>,
+) -> Option<PointerWindow> {
+    let window = window?;
+    return Some(PointerWindow(
+        pointer.root_x - window.x,
+        pointer.root_y - window.y,
+        window.width,
+        window.height,
+    ));
+}
+
+/// Get the applications and windows excluded from the collection.
+fn get_exclusion(config: &config::Config) -> Result<privacy::Exclusion, String> {
+    return privacy::Exclusion::new(
+        config.exclude_wm_classes.as_ref().unwrap(),
+        config.exclude_title.as_ref().unwrap(),
+        config.exclude_executables.as_ref().unwrap(),
+    );
+}
+
+fn collect(tx: std::sync::mpsc::Sender<utils::Message>, config: config::Config) -> () {
+    // The exclusion is validated at startup, but never collect anything the
+    // user wanted to exclude.
+    let exclusion = match get_exclusion(&config) {
+        Ok(exclusion) => exclusion,
+        Err(error) => {
+            println!("Not collecting input events: {}", error);
+            return;
+        }
+    };
+    let exclude_gap_marker = config.exclude_gap_marker.unwrap();
+
     // Setup connection to the X server.
     let (connection, screen_number) = utils::setup_connection();
 
@@ -362,29 +855,248 @@ fn collect(tx: std::sync::mpsc::Sender<utils::Message>) -> () {
     // Apply specific event masks to the connection.
     utils::select_events(&connection, screen);
 
+    // Get notified about monitor configuration changes.
+    utils::select_randr_events(&connection, screen);
+
+    // Get notified when the screen saver activates or deactivates.
+    utils::select_screensaver_events(&connection, screen);
+
+    // Track the active window only if applications or windows are excluded
+    // from the collection.
+    let mut privacy_filter = match exclusion.is_empty() {
+        true => None,
+        false => Some(privacy::PrivacyFilter::new(
+            &connection,
+            screen.root,
+            exclusion,
+        )),
+    };
+
+    // Report the changes of the active window only if enabled.
+    let mut focus_tracker = match config.focus_events_enabled.unwrap() {
+        true => match focus::load_key() {
+            Ok(key) => Some(focus::FocusTracker::new(
+                &connection,
+                screen.root,
+                &config.focus_allowed_wm_classes.unwrap(),
+                key,
+            )),
+            Err(error) => {
+                println!(
+                    "Could not load application key, not tracking the active window: {}",
+                    error
+                );
+                None
+            }
+        },
+        false => None,
+    };
+
+    // Track the geometries of the top-level windows only if the pointer
+    // coordinates relative to them are reported.
+    let mut geometry_cache = match config.window_coordinates_enabled.unwrap() {
+        true => Some(window::GeometryCache::new(&connection, screen.root)),
+        false => None,
+    };
+
     // Send pending requests to the X server.
     match connection.flush() {
-        Ok(result) => drop(result),
+        Ok(()) => (),
         Err(error) => panic!("Error, flush did not succeed: {:?}", error),
     }
 
+    let _liveness = health::start(health::Subsystem::Collector);
+
+    // The metadata was queried before the collector thread was started, so
+    // the application is ready once the events are selected.
+    systemd::notify_ready();
+
+    let mut pointer_queue = pointer::PointerQueue::new(&connection, screen.root);
+
     loop {
-        // Wait for a new event, the program should not panic on connection
-        // error.
-        let event = match connection.wait_for_event() {
-            Ok(event) => event,
+        // Wait for a new event only if no event is waiting for its pointer,
+        // otherwise read the events that are already pending.
+        let event = match pointer_queue.is_empty() {
+            true => connection.wait_for_event().map(Some),
+            false => connection.poll_for_event(),
+        };
+
+        // Send the queued events once no more events are pending, and before
+        // handling any other event to keep the order of the events.
+        let input_event = match &event {
+            Ok(Some(event)) => Some(is_input_event(event) || is_pointer_event(event)),
+            Ok(None) | Err(_) => None,
+        };
+        if pointer_queue.must_drain(input_event) {
+            pointer_queue.drain(|event, pointer| {
+                send_input_event(
+                    &tx,
+                    &connection,
+                    privacy_filter.as_mut(),
+                    geometry_cache.as_mut(),
+                    exclude_gap_marker,
+                    event,
+                    pointer,
+                )
+            });
+        }
+
+        // The program should not panic on connection error.
+        let event = match event {
+            Ok(Some(event)) => event,
+            Ok(None) => continue,
             Err(error) => {
                 println!("Connection error: {:?}", error);
                 continue;
             }
         };
 
-        // Get the transformed pointer coordinates too for comparison.
-        let pointer = utils::get_pointer(&connection, screen.root);
+        // The pointer events report the position of the pointer for the raw
+        // events preceding them.
+        if let Event::XinputMotion(event) = &event {
+            pointer_queue.update(event.time, pointer::Pointer::from_device_event(event));
+            continue;
+        }
 
-        match tx.send(utils::Message::X11EventMessage(event, pointer)) {
-            Ok(()) => (),
-            Err(err) => println!("Could not send message: {}", err),
+        // Re-apply the event masks when input devices are added or removed, so
+        // that hotplugged devices are tracked as well. The event is reported
+        // without querying the pointer.
+        if let Event::XinputHierarchy(event) = event {
+            utils::select_events(&connection, screen);
+            match connection.flush() {
+                Ok(()) => (),
+                Err(error) => println!("Error, flush did not succeed: {:?}", error),
+            }
+            utils::send(&tx, utils::Message::HierarchyMessage(event));
+            continue;
+        }
+
+        // Query metadata again when the monitor configuration changes.
+        match event {
+            Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_) => {
+                utils::send(&tx, utils::Message::MetadataChangedMessage);
+                continue;
+            }
+            _ => (),
+        }
+
+        // Report screen saver state changes as idle and active transitions.
+        if let Event::ScreensaverNotify(event) = event {
+            let session_event = match event.state {
+                screensaver::State::ON => session::SessionEvent::Idle,
+                screensaver::State::OFF => session::SessionEvent::Active,
+                _ => continue,
+            };
+            utils::send(
+                &tx,
+                utils::Message::SessionEventMessage(session_event, None),
+            );
+            continue;
+        }
+
+        // Window events are only selected by the privacy filter, the focus
+        // tracker and the geometry cache.
+        match event {
+            Event::PropertyNotify(_)
+            | Event::DestroyNotify(_)
+            | Event::ConfigureNotify(_)
+            | Event::CreateNotify(_)
+            | Event::MapNotify(_)
+            | Event::UnmapNotify(_)
+            | Event::ReparentNotify(_)
+            | Event::GravityNotify(_)
+            | Event::CirculateNotify(_) => {
+                if let Some(geometry_cache) = &mut geometry_cache {
+                    geometry_cache.handle_event(&event);
+                }
+                if let Some(privacy_filter) = &mut privacy_filter {
+                    privacy_filter.handle_event(&connection, &event);
+                }
+                if let (Some(focus_tracker), Event::PropertyNotify(event)) =
+                    (&mut focus_tracker, &event)
+                {
+                    let active_window =
+                        focus_tracker.handle_event(&connection, event, privacy_filter.as_mut());
+                    if let Some(active_window) = active_window {
+                        utils::send(&tx, utils::Message::ActiveWindowMessage(active_window));
+                    }
+                }
+                continue;
+            }
+            _ => (),
+        }
+
+        // Wait for the pointer event following the raw event.
+        if let Some(time) = get_input_event_time(&event) {
+            pointer_queue.push(event, time as u32);
+        }
+    }
+}
+
+/// Whether the event is sent to the main event loop along with the pointer.
+fn is_input_event(event: &Event) -> bool {
+    return matches!(
+        event,
+        Event::XinputRawMotion(_)
+            | Event::XinputRawTouchBegin(_)
+            | Event::XinputRawTouchUpdate(_)
+            | Event::XinputRawTouchEnd(_)
+            | Event::XinputRawButtonPress(_)
+            | Event::XinputRawButtonRelease(_)
+    );
+}
+
+/// Whether the event reports the position of the pointer for a raw event.
+fn is_pointer_event(event: &Event) -> bool {
+    return matches!(event, Event::XinputMotion(_));
+}
+
+/// Get the X server time of the raw input event, `None` for other events.
+fn get_input_event_time(event: &Event) -> Option<u64> {
+    let time = match event {
+        Event::XinputRawMotion(event) => event.time,
+        Event::XinputRawTouchBegin(event) => event.time,
+        Event::XinputRawTouchUpdate(event) => event.time,
+        Event::XinputRawTouchEnd(event) => event.time,
+        Event::XinputRawButtonPress(event) => event.time,
+        Event::XinputRawButtonRelease(event) => event.time,
+        _ => return None,
+    };
+    return Some(time.into());
+}
+
+/// Send the input event to the main event loop, unless it happened over an
+/// excluded window.
+fn send_input_event(
+    tx: &mpsc::Sender<utils::Message>,
+    connection: &RustConnection,
+    privacy_filter: Option<&mut privacy::PrivacyFilter>,
+    geometry_cache: Option<&mut window::GeometryCache>,
+    exclude_gap_marker: bool,
+    event: Event,
+    pointer: pointer::Pointer,
+) -> () {
+    // Drop input events over excluded windows.
+    if let Some(privacy_filter) = privacy_filter {
+        if let Some(time) = get_input_event_time(&event) {
+            match privacy_filter.check(connection, pointer.child, time) {
+                privacy::Verdict::Collect => (),
+                privacy::Verdict::Drop => return,
+                privacy::Verdict::EndGap(start) => {
+                    if exclude_gap_marker {
+                        let message = utils::Message::PrivacyGapMessage(start, time);
+                        utils::send(tx, message);
+                    }
+                }
+            }
         }
     }
+
+    // Get the geometry of the window under the pointer.
+    let window = match geometry_cache {
+        Some(geometry_cache) => geometry_cache.get(connection, pointer.child),
+        None => None,
+    };
+
+    utils::send(tx, utils::Message::X11EventMessage(event, pointer, window));
 }
@@ -0,0 +1,326 @@
+/**
+ * Pointer positions of the raw input events.
+ *
+ * Raw input events do not carry the position of the pointer. The X server
+ * follows every raw event with the corresponding XI2 pointer event, which does,
+ * as long as no other client below the pointer selected the pointer events
+ * itself or grabbed the pointer. The queue keeps the raw events until their
+ * pointer event arrives, so no round trip to the X server is needed.
+ *
+ * Raw events without a pointer event, e.g. over windows of toolkits selecting
+ * XI2 events, fall back to a single `QueryPointer` request once the queue is
+ * drained, so a burst of such events costs a single round trip.
+ */
+use std::collections::VecDeque;
+use x11rb::protocol::xinput;
+use x11rb::protocol::xproto;
+use x11rb::protocol::xproto::{QueryPointerReply, Timestamp, Window};
+use x11rb::rust_connection::RustConnection;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Maximum number of events waiting for their pointer. The queue is drained
+/// once it is full, so that a continuous stream of events is not delayed
+/// indefinitely.
+pub const MAX_PENDING_EVENTS: usize = 256;
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Position of the pointer along with the top-level window containing it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pointer {
+    pub root_x: i16,
+    pub root_y: i16,
+    /// Child of the root window containing the pointer, `NONE` if the
+    /// pointer is over the root window itself.
+    pub child: Window,
+}
+
+impl Pointer {
+    /// Position reported by an XI2 pointer event selected on the root window.
+    pub fn from_device_event(event: &xinput::MotionEvent) -> Pointer {
+        Pointer {
+            root_x: fp1616_to_i16(event.root_x),
+            root_y: fp1616_to_i16(event.root_y),
+            child: event.child,
+        }
+    }
+}
+
+impl From<QueryPointerReply> for Pointer {
+    fn from(reply: QueryPointerReply) -> Pointer {
+        Pointer {
+            root_x: reply.root_x,
+            root_y: reply.root_y,
+            child: reply.child,
+        }
+    }
+}
+
+/// Item waiting for the pointer event sent along with it.
+struct PendingItem<T> {
+    item: T,
+    time: Timestamp,
+    pointer: Option<Pointer>,
+}
+
+/// Items waiting for the position of the pointer at their time.
+pub struct PointerQueue<'c, T> {
+    connection: &'c RustConnection,
+    window: Window,
+    pending: VecDeque<PendingItem<T>>,
+    /// Number of items up to the last one that got its pointer. The pointer
+    /// events arrive in order, so the items before it miss theirs.
+    resolved: usize,
+}
+
+impl<'c, T> PointerQueue<'c, T> {
+    /// Constructor for the `PointerQueue` object. Missing positions are
+    /// queried relative to the given window.
+    pub fn new(connection: &'c RustConnection, window: Window) -> PointerQueue<'c, T> {
+        PointerQueue {
+            connection,
+            window,
+            pending: VecDeque::new(),
+            resolved: 0,
+        }
+    }
+
+    /// Queue the item that happened at the given X server time until the
+    /// position of the pointer is known.
+    pub fn push(&mut self, item: T, time: Timestamp) -> () {
+        self.pending.push_back(PendingItem {
+            item,
+            time,
+            pointer: None,
+        });
+    }
+
+    /// Record the position reported by a pointer event for the oldest item
+    /// of the same time still missing it. Pointer events without a queued
+    /// item are ignored.
+    pub fn update(&mut self, time: Timestamp, pointer: Pointer) -> () {
+        let index = self
+            .pending
+            .iter()
+            .skip(self.resolved)
+            .position(|pending| pending.time == time);
+        if let Some(index) = index {
+            let index = self.resolved + index;
+            self.pending[index].pointer = Some(pointer);
+            self.resolved = index + 1;
+        }
+    }
+
+    /// Whether the queue has to be drained before handling the next event,
+    /// given whether it is an input or pointer event, or `None` if no event
+    /// is pending. Other events are handled after the queued items to keep
+    /// the order of the events.
+    pub fn must_drain(&self, input_event: Option<bool>) -> bool {
+        return input_event != Some(true) || self.pending.len() >= MAX_PENDING_EVENTS;
+    }
+
+    /// Hand the items to the handler in their order along with the pointer.
+    /// The pointer is queried once for all items whose pointer event did not
+    /// arrive. Items are dropped if the query fails.
+    pub fn drain<F: FnMut(T, Pointer)>(&mut self, mut handler: F) -> () {
+        let mut queried: Option<Option<Pointer>> = None;
+        while let Some(pending) = self.pending.pop_front() {
+            let pointer = match pending.pointer {
+                Some(pointer) => Some(pointer),
+                None => *queried.get_or_insert_with(|| self.query()),
+            };
+            if let Some(pointer) = pointer {
+                handler(pending.item, pointer);
+            }
+        }
+        self.resolved = 0;
+    }
+
+    /// Whether no item is waiting for its pointer.
+    pub fn is_empty(&self) -> bool {
+        return self.pending.is_empty();
+    }
+
+    /// Query the current position of the pointer.
+    fn query(&self) -> Option<Pointer> {
+        let reply = xproto::query_pointer(self.connection, self.window)
+            .map_err(|error| error.into())
+            .and_then(|cookie| cookie.reply());
+        match reply {
+            Ok(reply) => return Some(Pointer::from(reply)),
+            Err(error) => {
+                println!("Could not query pointer: {:?}", error);
+                return None;
+            }
+        }
+    }
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Integral part of a 16.16 fixed point number.
+fn fp1616_to_i16(value: xinput::Fp1616) -> i16 {
+    return (value >> 16) as i16;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_x_server;
+
+    fn pointer(root_x: i16) -> Pointer {
+        return Pointer {
+            root_x,
+            root_y: 200,
+            child: 0x200,
+        };
+    }
+
+    /// Kind of the events read from the connection.
+    enum Input {
+        /// Raw input event at the given time.
+        Raw(u32),
+        /// Pointer event at the given time and x coordinate.
+        Pointer(u32, i16),
+        /// Any other event.
+        Other(u32),
+    }
+
+    /// Handle the events the way the collector does. Returns the raw and
+    /// other events in the order they are handled, raw events along with the
+    /// x coordinate of the pointer.
+    fn handle_events(connection: &RustConnection, events: &[Input]) -> Vec<(u32, Option<i16>)> {
+        let mut handled = Vec::new();
+        let mut queue = PointerQueue::new(connection, test_x_server::ROOT_WINDOW);
+        for event in events {
+            let input_event = !matches!(event, Input::Other(_));
+            if queue.must_drain(Some(input_event)) {
+                queue.drain(|time, pointer| handled.push((time, Some(pointer.root_x))));
+            }
+            match *event {
+                Input::Raw(time) => queue.push(time, time),
+                Input::Pointer(time, x) => queue.update(time, pointer(x)),
+                Input::Other(time) => handled.push((time, None)),
+            }
+        }
+        assert!(queue.must_drain(None));
+        queue.drain(|time, pointer| handled.push((time, Some(pointer.root_x))));
+        return handled;
+    }
+
+    #[test]
+    fn positions_are_taken_from_pointer_events() {
+        let connection = test_x_server::connect_closing_after(Some(0));
+        let mut events = Vec::new();
+        for time in 0..1000 {
+            match time % 100 {
+                50 => events.push(Input::Other(time)),
+                _ => {
+                    events.push(Input::Raw(time));
+                    events.push(Input::Pointer(time, time as i16));
+                }
+            }
+        }
+
+        // The X server is never asked, it closed the connection anyway.
+        let handled = handle_events(&connection, &events);
+        let expected: Vec<(u32, Option<i16>)> = (0..1000)
+            .map(|time| match time % 100 {
+                50 => (time, None),
+                _ => (time, Some(time as i16)),
+            })
+            .collect();
+        assert_eq!(handled, expected);
+    }
+
+    #[test]
+    fn events_of_the_same_time_get_their_own_positions() {
+        let connection = test_x_server::connect_closing_after(Some(0));
+        let events = [
+            Input::Raw(1),
+            Input::Raw(1),
+            Input::Pointer(1, 10),
+            Input::Pointer(1, 11),
+            Input::Pointer(2, 20),
+        ];
+        let handled = handle_events(&connection, &events);
+        assert_eq!(handled, vec![(1, Some(10)), (1, Some(11))]);
+    }
+
+    #[test]
+    fn missing_positions_are_queried_once_per_drain() {
+        let connection = test_x_server::connect();
+        let events = [
+            Input::Raw(1),
+            Input::Pointer(1, 10),
+            Input::Raw(2),
+            Input::Raw(3),
+            Input::Other(4),
+            Input::Raw(5),
+        ];
+        let handled = handle_events(&connection, &events);
+
+        // The mock server reports the sequence number of the request as the
+        // x coordinate, the first drain sends the first request.
+        assert_eq!(
+            handled,
+            vec![
+                (1, Some(10)),
+                (2, Some(1)),
+                (3, Some(1)),
+                (4, None),
+                (5, Some(2))
+            ]
+        );
+    }
+
+    #[test]
+    fn queue_is_drained_when_needed() {
+        let connection = test_x_server::connect();
+        let mut queue = PointerQueue::new(&connection, test_x_server::ROOT_WINDOW);
+        assert!(!queue.must_drain(Some(true)));
+        assert!(queue.must_drain(Some(false)));
+        assert!(queue.must_drain(None));
+
+        for event in 0..MAX_PENDING_EVENTS - 1 {
+            queue.push(event, 0);
+        }
+        assert!(!queue.must_drain(Some(true)));
+        queue.push(MAX_PENDING_EVENTS - 1, 0);
+        assert!(queue.must_drain(Some(true)));
+
+        let mut drained = Vec::new();
+        queue.drain(|event, _pointer| drained.push(event));
+        assert_eq!(drained, (0..MAX_PENDING_EVENTS).collect::<Vec<usize>>());
+        assert!(queue.is_empty());
+        assert!(!queue.must_drain(Some(true)));
+    }
+
+    #[test]
+    fn connection_errors_drop_events() {
+        let connection = test_x_server::connect_closing_after(Some(0));
+        let mut queue = PointerQueue::new(&connection, test_x_server::ROOT_WINDOW);
+        let mut drained = Vec::new();
+        for event in 0..4 {
+            queue.push(event, event);
+            queue.drain(|event, _pointer| drained.push(event));
+        }
+        assert!(drained.is_empty());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn fixed_point_coordinates_are_truncated() {
+        assert_eq!(fp1616_to_i16(1920 << 16), 1920);
+        assert_eq!(fp1616_to_i16((100 << 16) + 0x8000), 100);
+        assert_eq!(fp1616_to_i16(0), 0);
+    }
+}
@@ -0,0 +1,339 @@
+/**
+ * This module decides whether input events are collected, based on the
+ * applications and windows excluded from the collection by the user.
+ *
+ * An event is excluded if either the active window or the window under the
+ * pointer is excluded. Decisions are cached per window and invalidated when
+ * the properties they are based on change.
+ */
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use x11rb::protocol::xproto::{Atom, AtomEnum, EventMask, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::data_collector::window;
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+/// What to do with an input event.
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// Collect the event.
+    Collect,
+    /// Drop the event, it happened over an excluded window.
+    Drop,
+    /// Collect the event, it ends the gap started at the given X server time
+    /// in milliseconds.
+    EndGap(u64),
+}
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Applications and windows excluded from the collection.
+pub struct Exclusion {
+    wm_classes: Vec<String>,
+    title: Option<Regex>,
+    executables: Vec<String>,
+}
+
+impl Exclusion {
+    /// Constructor for the `Exclusion` object. The WM_CLASS names and the
+    /// executables are comma separated lists, the title is a regular
+    /// expression. Empty values exclude nothing. An error is returned if the
+    /// title is not a valid regular expression.
+    pub fn new(wm_classes: &str, title: &str, executables: &str) -> Result<Exclusion, String> {
+        let title = match title.is_empty() {
+            true => None,
+            false => match Regex::new(title) {
+                Ok(regex) => Some(regex),
+                Err(error) => return Err(format!("invalid window title exclusion: {}", error)),
+            },
+        };
+        Ok(Exclusion {
+            wm_classes: split_list(wm_classes)
+                .map(|name| name.to_lowercase())
+                .collect(),
+            title,
+            executables: split_list(executables).map(String::from).collect(),
+        })
+    }
+
+    /// Whether nothing is excluded.
+    pub fn is_empty(&self) -> bool {
+        return self.wm_classes.is_empty() && self.title.is_none() && self.executables.is_empty();
+    }
+
+    /// Whether the client window is excluded. The window properties are only
+    /// queried if something is excluded by them.
+    fn matches(&self, connection: &RustConnection, window: Window, atoms: &window::Atoms) -> bool {
+        if !self.wm_classes.is_empty()
+            && self.matches_wm_class(&window::get_wm_class(connection, window))
+        {
+            return true;
+        }
+        if self.title.is_some() {
+            if let Some(window_title) = window::get_title(connection, window, atoms) {
+                if self.matches_title(&window_title) {
+                    return true;
+                }
+            }
+        }
+        if !self.executables.is_empty() {
+            if let Some(pid) = window::get_pid(connection, window, atoms) {
+                if let Ok(executable) = fs::read_link(format!("/proc/{}/exe", pid)) {
+                    return self.matches_executable(&executable);
+                }
+            }
+        }
+        return false;
+    }
+
+    /// Whether one of the `WM_CLASS` names is excluded.
+    fn matches_wm_class(&self, wm_class: &[String]) -> bool {
+        return wm_class
+            .iter()
+            .any(|name| self.wm_classes.contains(&name.to_lowercase()));
+    }
+
+    /// Whether the window title is excluded.
+    fn matches_title(&self, window_title: &str) -> bool {
+        return self
+            .title
+            .as_ref()
+            .is_some_and(|title| title.is_match(window_title));
+    }
+
+    /// Whether the executable is excluded.
+    fn matches_executable(&self, executable: &Path) -> bool {
+        return self
+            .executables
+            .iter()
+            .any(|entry| matches_executable(entry, executable));
+    }
+}
+
+/// Start of the gap in the collection while excluded windows are in use.
+#[derive(Default)]
+struct Gap {
+    start: Option<u64>,
+}
+
+impl Gap {
+    /// Decide about an event at the given X server time in milliseconds, the
+    /// clock of the input events. The first collected event after excluded
+    /// ones ends the gap.
+    fn update(&mut self, excluded: bool, time: u64) -> Verdict {
+        if excluded {
+            if self.start.is_none() {
+                self.start = Some(time);
+            }
+            return Verdict::Drop;
+        }
+        match self.start.take() {
+            Some(start) => return Verdict::EndGap(start),
+            None => return Verdict::Collect,
+        }
+    }
+}
+
+/// Cached decision about a window.
+struct Decision {
+    client: Option<Window>,
+    excluded: bool,
+}
+
+/// Tracks the active window and the excluded windows.
+pub struct PrivacyFilter {
+    exclusion: Exclusion,
+    atoms: window::Atoms,
+    root: Window,
+    active_window: Option<Window>,
+    decisions: HashMap<Window, Decision>,
+    gap: Gap,
+}
+
+impl PrivacyFilter {
+    /// Constructor for the `PrivacyFilter` object. Selects property change
+    /// events on the root window to track the active window.
+    pub fn new(connection: &RustConnection, root: Window, exclusion: Exclusion) -> PrivacyFilter {
+        let atoms = window::get_atoms(connection);
+        window::select_window_events(connection, root, EventMask::PROPERTY_CHANGE);
+        let active_window = window::get_active_window(connection, root, &atoms);
+        PrivacyFilter {
+            exclusion,
+            atoms,
+            root,
+            active_window,
+            decisions: HashMap::new(),
+            gap: Gap::default(),
+        }
+    }
+
+    /// Event handler for the window events selected by the filter.
+    pub fn handle_event(&mut self, connection: &RustConnection, event: &Event) -> () {
+        match event {
+            Event::PropertyNotify(event)
+                if event.window == self.root && event.atom == self.atoms._NET_ACTIVE_WINDOW =>
+            {
+                self.active_window = window::get_active_window(connection, self.root, &self.atoms);
+            }
+            Event::PropertyNotify(event)
+                if event.window != self.root && self.is_relevant_property(event.atom) =>
+            {
+                self.forget(event.window);
+            }
+            Event::DestroyNotify(event) => {
+                self.forget(event.window);
+                self.decisions.remove(&event.window);
+            }
+            _ => (),
+        }
+    }
+
+    /// Decide whether an input event that happened at the given X server
+    /// time is collected. The pointer window is the child of the root window
+    /// containing the pointer.
+    pub fn check(
+        &mut self,
+        connection: &RustConnection,
+        pointer_window: Window,
+        time: u64,
+    ) -> Verdict {
+        let mut excluded = self.is_excluded(connection, pointer_window);
+        if let Some(active_window) = self.active_window {
+            excluded = excluded || self.is_excluded(connection, active_window);
+        }
+        return self.gap.update(excluded, time);
+    }
+
+    /// Whether the window is excluded, using the cached decision if possible.
+    pub fn is_excluded(&mut self, connection: &RustConnection, window: Window) -> bool {
+        if window == x11rb::NONE || window == self.root {
+            return false;
+        }
+        if let Some(decision) = self.decisions.get(&window) {
+            return decision.excluded;
+        }
+
+        let client = window::get_client_window(connection, window, &self.atoms);
+        let excluded = match client {
+            Some(client) => {
+                // Get notified when the decision has to be reconsidered.
+                window::select_window_events(
+                    connection,
+                    client,
+                    EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY,
+                );
+                self.exclusion.matches(connection, client, &self.atoms)
+            }
+            None => false,
+        };
+        self.decisions.insert(window, Decision { client, excluded });
+        return excluded;
+    }
+
+    /// Forget the decisions based on the properties of the client window.
+    fn forget(&mut self, client: Window) -> () {
+        self.decisions
+            .retain(|_window, decision| decision.client != Some(client));
+    }
+
+    /// Whether the decisions depend on the property.
+    fn is_relevant_property(&self, atom: Atom) -> bool {
+        return atom == u32::from(AtomEnum::WM_CLASS)
+            || atom == u32::from(AtomEnum::WM_NAME)
+            || atom == self.atoms._NET_WM_NAME
+            || atom == self.atoms._NET_WM_PID;
+    }
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Split a comma separated list, skipping empty entries.
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    return list
+        .split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty());
+}
+
+/// Whether the executable matches the entry of the exclusion list. Entries
+/// containing a slash match the full path, others the file name.
+fn matches_executable(entry: &str, executable: &Path) -> bool {
+    if entry.contains('/') {
+        return executable == Path::new(entry);
+    }
+    return executable.file_name().is_some_and(|name| name == entry);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        return names.iter().map(|name| String::from(*name)).collect();
+    }
+
+    #[test]
+    fn wm_classes_are_matched_case_insensitively() {
+        let exclusion = Exclusion::new(" KeePassXC, zoom ,", "", "").unwrap();
+        assert!(exclusion.matches_wm_class(&names(&["keepassxc", "KeePassXC"])));
+        assert!(exclusion.matches_wm_class(&names(&["zoom", "Zoom"])));
+        assert!(!exclusion.matches_wm_class(&names(&["navigator", "Firefox"])));
+        assert!(!exclusion.matches_wm_class(&[]));
+    }
+
+    #[test]
+    fn titles_are_matched_by_regex() {
+        let exclusion = Exclusion::new("", "(?i)password|^Meet - ", "").unwrap();
+        assert!(exclusion.matches_title("Change Password - Mozilla Firefox"));
+        assert!(exclusion.matches_title("Meet - abc-defg-hij"));
+        assert!(!exclusion.matches_title("Google Meet - Firefox"));
+        assert!(!exclusion.matches_title(""));
+        assert!(!Exclusion::new("", "", "")
+            .unwrap()
+            .matches_title("Password"));
+    }
+
+    #[test]
+    fn invalid_title_regex_is_an_error() {
+        assert!(Exclusion::new("", "(unclosed", "").is_err());
+    }
+
+    #[test]
+    fn executables_are_matched_by_name_or_path() {
+        let exclusion = Exclusion::new("", "", "keepassxc, /opt/zoom/zoom").unwrap();
+        assert!(exclusion.matches_executable(Path::new("/usr/bin/keepassxc")));
+        assert!(exclusion.matches_executable(Path::new("/opt/zoom/zoom")));
+        assert!(!exclusion.matches_executable(Path::new("/usr/bin/zoom")));
+        assert!(!exclusion.matches_executable(Path::new("/usr/bin/keepassxc-cli")));
+    }
+
+    #[test]
+    fn empty_exclusion_is_empty() {
+        assert!(Exclusion::new("", "", " , ").unwrap().is_empty());
+        assert!(!Exclusion::new("", "x", "").unwrap().is_empty());
+        assert!(!Exclusion::new("", "", "zoom").unwrap().is_empty());
+    }
+
+    #[test]
+    fn gap_marker_spans_excluded_events() {
+        let mut gap = Gap::default();
+        assert_eq!(gap.update(false, 100), Verdict::Collect);
+        assert_eq!(gap.update(true, 200), Verdict::Drop);
+        assert_eq!(gap.update(true, 300), Verdict::Drop);
+        assert_eq!(gap.update(false, 400), Verdict::EndGap(200));
+        assert_eq!(gap.update(false, 500), Verdict::Collect);
+        assert_eq!(gap.update(true, 600), Verdict::Drop);
+        assert_eq!(gap.update(false, 700), Verdict::EndGap(600));
+    }
+}
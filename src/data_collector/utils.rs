@@ -1,23 +1,30 @@
 /**
  * Utility functions for the data collector module.
  */
-use std::env;
-use std::process::Command;
-use std::str::FromStr;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use x11rb::protocol::randr;
+use x11rb::protocol::screensaver;
 use x11rb::protocol::xinput;
-use x11rb::protocol::xproto;
 
 //==============================================================================
 // Enums
 //==============================================================================
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Clone, Debug)]
 pub enum Message {
+    ActiveWindowMessage(crate::data_collector::focus::ActiveWindow),
+    FlushMessage,
+    HierarchyMessage(xinput::HierarchyEvent),
     MetadataChangedMessage,
+    PrivacyGapMessage(u64, u64),
+    QueryStateMessage(mpsc::Sender<crate::data_collector::CollectorState>),
+    SessionEventMessage(crate::session::SessionEvent, Option<mpsc::Sender<()>>),
     X11EventMessage(
         x11rb::protocol::Event,
-        x11rb::protocol::xproto::QueryPointerReply,
+        crate::data_collector::pointer::Pointer,
+        Option<crate::data_collector::window::Geometry>,
     ),
 }
 
@@ -35,46 +42,22 @@ pub fn now() -> u64 {
         .unwrap();
 }
 
-/// Generic function to get an environment variable and parse it to the desired
-/// type.
-pub fn get_env_var<T: FromStr>(name: &str) -> T {
-    let var: T = match env::var(name) {
-        Ok(string) => match string.parse() {
-            Ok(value) => value,
-            Err(_error) => panic!("Could not parse {} environment variable.", name),
-        },
-        Err(error) => panic!("Could not find {} environment variable: {}", name, error),
-    };
-    return var;
-}
-
-/// Get the output of the `who` command as the unique identifier of the current
-/// user session
-pub fn get_session_id() -> String {
-    let output = Command::new("who")
-        .output()
-        .expect("Failed to execute command: who");
-
-    // Convert result to String.
-    let mut result = String::from_utf8(output.stdout).unwrap();
-
-    // Strip whitespace.
-    result.retain(|c| !c.is_whitespace());
-    return result;
+/// Send a message to the main event loop of the data collector.
+pub fn send(tx: &mpsc::Sender<Message>, message: Message) -> () {
+    match tx.send(message) {
+        Ok(()) => crate::metrics::CHANNEL_BACKLOG.inc(),
+        Err(error) => println!("Could not send message: {}", error),
+    }
 }
 
-/// Get the pointer from the X server.
-pub fn get_pointer(
-    connection: &x11rb::rust_connection::RustConnection,
-    window: u32,
-) -> x11rb::protocol::xproto::QueryPointerReply {
-    return match xproto::query_pointer(connection, window) {
-        Ok(cookie) => match cookie.reply() {
-            Ok(result) => result,
-            Err(error) => panic!("Could not get reply from server: {:?}", error),
-        },
-        Err(error) => panic!("Cold not query pointer: {:?}", error),
-    };
+/// Receive a message sent by `send`, waiting up to the given time.
+pub fn receive(
+    rx: &mpsc::Receiver<Message>,
+    timeout: Duration,
+) -> Result<Message, mpsc::RecvTimeoutError> {
+    let message = rx.recv_timeout(timeout)?;
+    crate::metrics::CHANNEL_BACKLOG.dec();
+    return Ok(message);
 }
 
 /// Setup connection to the X server and check extension availability.
@@ -110,7 +93,8 @@ pub fn select_events(
             xinput::DeviceUse::IS_X_POINTER => {
                 event_masks.push(xinput::EventMask {
                     deviceid: device.device_id.into(),
-                    mask: vec![(xinput::XIEventMask::RAW_MOTION
+                    mask: vec![(xinput::XIEventMask::MOTION
+                        | xinput::XIEventMask::RAW_MOTION
                         | xinput::XIEventMask::RAW_TOUCH_BEGIN
                         | xinput::XIEventMask::RAW_TOUCH_UPDATE
                         | xinput::XIEventMask::RAW_TOUCH_END
@@ -123,16 +107,81 @@ pub fn select_events(
         };
     }
 
+    // Create an event mask for device hierarchy changes so that hotplugged
+    // input devices can be tracked.
+    event_masks.push(xinput::EventMask {
+        deviceid: xinput::Device::ALL.into(),
+        mask: vec![xinput::XIEventMask::HIERARCHY.into()],
+    });
+
     // Apply event masks.
     match xinput::xi_select_events(connection, screen.root, &event_masks) {
         Ok(cookie) => match cookie.check() {
-            Ok(result) => drop(result),
+            Ok(()) => (),
             Err(error) => panic!("Could not apply event masks: {:?}", error),
         },
         Err(error) => panic!("Could not connect to server: {:?}", error),
     };
 }
 
+/// Select RandR notifications on the root window to get notified about
+/// screen, CRTC and output changes.
+pub fn select_randr_events(
+    connection: &x11rb::rust_connection::RustConnection,
+    screen: &x11rb::protocol::xproto::Screen,
+) -> () {
+    // The RandR version has to be negotiated before selecting events.
+    match randr::query_version(connection, 1, 5) {
+        Ok(cookie) => match cookie.reply() {
+            Ok(_version) => (),
+            Err(error) => panic!("Could not get reply from server: {:?}", error),
+        },
+        Err(error) => panic!("Could not query RandR version: {:?}", error),
+    };
+
+    let mask = randr::NotifyMask::SCREEN_CHANGE
+        | randr::NotifyMask::CRTC_CHANGE
+        | randr::NotifyMask::OUTPUT_CHANGE;
+    match randr::select_input(connection, screen.root, mask) {
+        Ok(cookie) => match cookie.check() {
+            Ok(()) => (),
+            Err(error) => panic!("Could not select RandR events: {:?}", error),
+        },
+        Err(error) => panic!("Could not connect to server: {:?}", error),
+    };
+}
+
+/// Select screen saver notifications on the root window to get notified when
+/// the screen saver activates or deactivates.
+pub fn select_screensaver_events(
+    connection: &x11rb::rust_connection::RustConnection,
+    screen: &x11rb::protocol::xproto::Screen,
+) -> () {
+    // The screen saver extension is optional, idle events are not reported
+    // without it.
+    match screensaver::query_version(connection, 1, 1) {
+        Ok(cookie) => match cookie.reply() {
+            Ok(_version) => (),
+            Err(error) => {
+                println!("Could not get reply from server: {:?}", error);
+                return;
+            }
+        },
+        Err(error) => {
+            println!("Could not query screen saver version: {:?}", error);
+            return;
+        }
+    };
+
+    match screensaver::select_input(connection, screen.root, screensaver::Event::NOTIFY_MASK) {
+        Ok(cookie) => match cookie.check() {
+            Ok(()) => (),
+            Err(error) => println!("Could not select screen saver events: {:?}", error),
+        },
+        Err(error) => println!("Could not connect to server: {:?}", error),
+    };
+}
+
 //==============================================================================
 // Internal functions
 //==============================================================================
@@ -149,3 +198,41 @@ fn check_xinput(connection: &x11rb::rust_connection::RustConnection) -> () {
         Err(error) => panic!("Could not query XInput version: {:?}", error),
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data_collector::TEST_CHANNEL_LOCK;
+    use crate::metrics::CHANNEL_BACKLOG;
+
+    #[test]
+    fn channel_backlog_counts_waiting_messages() {
+        let _lock = TEST_CHANNEL_LOCK
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let backlog = CHANNEL_BACKLOG.get();
+        let (tx, rx) = mpsc::channel();
+
+        send(&tx, Message::FlushMessage);
+        send(&tx, Message::MetadataChangedMessage);
+        assert_eq!(CHANNEL_BACKLOG.get(), backlog + 2);
+
+        assert!(matches!(
+            receive(&rx, Duration::ZERO),
+            Ok(Message::FlushMessage)
+        ));
+        assert_eq!(CHANNEL_BACKLOG.get(), backlog + 1);
+        assert!(matches!(
+            receive(&rx, Duration::ZERO),
+            Ok(Message::MetadataChangedMessage)
+        ));
+        assert_eq!(CHANNEL_BACKLOG.get(), backlog);
+
+        // Neither timeouts nor failed sends change the backlog.
+        assert!(receive(&rx, Duration::from_millis(10)).is_err());
+        drop(rx);
+        send(&tx, Message::FlushMessage);
+        assert_eq!(CHANNEL_BACKLOG.get(), backlog);
+    }
+}
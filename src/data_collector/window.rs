@@ -0,0 +1,366 @@
+/**
+ * Functions for querying the properties of top-level windows set by the
+ * clients and the window manager.
+ */
+use std::collections::HashMap;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, EventMask, GetPropertyReply, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Maximum depth of the window tree searched for the client window.
+const CLIENT_WINDOW_SEARCH_DEPTH: u32 = 4;
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Position of a window relative to the root window and its size.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Geometry {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+/// Geometries of the children of the root window, kept up to date by the
+/// structure events of the root window.
+pub struct GeometryCache {
+    root: Window,
+    geometries: HashMap<Window, Geometry>,
+}
+
+impl GeometryCache {
+    /// Constructor for the `GeometryCache` object. Selects the structure
+    /// events of the children of the root window.
+    pub fn new(connection: &RustConnection, root: Window) -> GeometryCache {
+        select_window_events(connection, root, EventMask::SUBSTRUCTURE_NOTIFY);
+        GeometryCache {
+            root,
+            geometries: HashMap::new(),
+        }
+    }
+
+    /// Event handler for the structure events of the root window.
+    pub fn handle_event(&mut self, event: &Event) -> () {
+        match event {
+            Event::ConfigureNotify(event) if event.event == self.root => {
+                // The position is the outer corner of the border, while the
+                // queried geometry starts inside the border.
+                let border_width = event.border_width as i16;
+                self.geometries.insert(
+                    event.window,
+                    Geometry {
+                        x: event.x + border_width,
+                        y: event.y + border_width,
+                        width: event.width,
+                        height: event.height,
+                    },
+                );
+            }
+            Event::DestroyNotify(event) if event.event == self.root => {
+                self.geometries.remove(&event.window);
+            }
+            Event::ReparentNotify(event) if event.event == self.root => {
+                self.geometries.remove(&event.window);
+            }
+            _ => (),
+        }
+    }
+
+    /// Get the geometry of a child of the root window, querying the X server
+    /// if it is not known yet.
+    pub fn get(&mut self, connection: &RustConnection, window: Window) -> Option<Geometry> {
+        if window == x11rb::NONE {
+            return None;
+        }
+        if let Some(geometry) = self.geometries.get(&window) {
+            return Some(geometry.clone());
+        }
+        let geometry = get_geometry(connection, window, self.root)?;
+        self.geometries.insert(window, geometry.clone());
+        return Some(geometry);
+    }
+}
+
+x11rb::atom_manager! {
+    pub Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        UTF8_STRING,
+        WM_STATE,
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Intern the atoms of the window properties.
+pub fn get_atoms(connection: &RustConnection) -> Atoms {
+    match Atoms::new(connection) {
+        Ok(cookie) => match cookie.reply() {
+            Ok(atoms) => return atoms,
+            Err(error) => panic!("Could not get reply from server: {:?}", error),
+        },
+        Err(error) => panic!("Could not intern atoms: {:?}", error),
+    }
+}
+
+/// Add the given events to the events selected on the window.
+pub fn select_window_events(connection: &RustConnection, window: Window, mask: EventMask) -> () {
+    let attributes = match connection.get_window_attributes(window) {
+        Ok(cookie) => match cookie.reply() {
+            Ok(attributes) => attributes,
+            // The window may have been destroyed in the meantime.
+            Err(_error) => return,
+        },
+        Err(error) => {
+            println!("Could not get window attributes: {:?}", error);
+            return;
+        }
+    };
+    let event_mask = EventMask::from(attributes.your_event_mask) | mask;
+    let values = xproto::ChangeWindowAttributesAux::new().event_mask(event_mask);
+    match connection.change_window_attributes(window, &values) {
+        Ok(_cookie) => (),
+        Err(error) => println!("Could not select window events: {:?}", error),
+    }
+    match connection.flush() {
+        Ok(()) => (),
+        Err(error) => println!("Error, flush did not succeed: {:?}", error),
+    }
+}
+
+/// Get the active window according to the window manager.
+pub fn get_active_window(
+    connection: &RustConnection,
+    root: Window,
+    atoms: &Atoms,
+) -> Option<Window> {
+    let reply = get_property(
+        connection,
+        root,
+        atoms._NET_ACTIVE_WINDOW,
+        AtomEnum::WINDOW.into(),
+    )?;
+    return reply
+        .value32()?
+        .next()
+        .filter(|window| *window != x11rb::NONE);
+}
+
+/// Find the client window of a top-level window. Reparenting window managers
+/// put the client window into a frame window, the client window is the one
+/// having the `WM_STATE` property.
+pub fn get_client_window(
+    connection: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+) -> Option<Window> {
+    return find_client_window(connection, window, atoms, CLIENT_WINDOW_SEARCH_DEPTH);
+}
+
+/// Get the instance and class names of the window.
+pub fn get_wm_class(connection: &RustConnection, window: Window) -> Vec<String> {
+    let reply = match get_property(
+        connection,
+        window,
+        AtomEnum::WM_CLASS.into(),
+        AtomEnum::STRING.into(),
+    ) {
+        Some(reply) => reply,
+        None => return Vec::new(),
+    };
+    return reply
+        .value
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect();
+}
+
+/// Get the title of the window, preferring the UTF-8 encoded `_NET_WM_NAME`.
+pub fn get_title(connection: &RustConnection, window: Window, atoms: &Atoms) -> Option<String> {
+    let reply =
+        get_property(connection, window, atoms._NET_WM_NAME, atoms.UTF8_STRING).or_else(|| {
+            get_property(
+                connection,
+                window,
+                AtomEnum::WM_NAME.into(),
+                AtomEnum::STRING.into(),
+            )
+        })?;
+    return Some(String::from_utf8_lossy(&reply.value).into_owned());
+}
+
+/// Get the geometry of the window, relative to the root window.
+pub fn get_geometry(connection: &RustConnection, window: Window, root: Window) -> Option<Geometry> {
+    let geometry = connection.get_geometry(window).ok()?.reply().ok()?;
+    let position = connection
+        .translate_coordinates(window, root, 0, 0)
+        .ok()?
+        .reply()
+        .ok()?;
+    return Some(Geometry {
+        x: position.dst_x,
+        y: position.dst_y,
+        width: geometry.width,
+        height: geometry.height,
+    });
+}
+
+/// Get the ID of the process owning the window.
+pub fn get_pid(connection: &RustConnection, window: Window, atoms: &Atoms) -> Option<u32> {
+    let reply = get_property(
+        connection,
+        window,
+        atoms._NET_WM_PID,
+        AtomEnum::CARDINAL.into(),
+    )?;
+    return reply.value32()?.next();
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Search the window and its descendants for the client window.
+fn find_client_window(
+    connection: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+    depth: u32,
+) -> Option<Window> {
+    if get_property(connection, window, atoms.WM_STATE, AtomEnum::ANY.into()).is_some() {
+        return Some(window);
+    }
+    if depth == 0 {
+        return None;
+    }
+    let tree = connection.query_tree(window).ok()?.reply().ok()?;
+    return tree
+        .children
+        .iter()
+        .rev()
+        .find_map(|child| find_client_window(connection, *child, atoms, depth - 1));
+}
+
+/// Get a property of the window, `None` if the window does not have it.
+fn get_property(
+    connection: &RustConnection,
+    window: Window,
+    property: u32,
+    type_: u32,
+) -> Option<GetPropertyReply> {
+    let reply = connection
+        .get_property(false, window, property, type_, 0, u32::MAX)
+        .ok()?
+        .reply()
+        .ok()?;
+    if reply.type_ == x11rb::NONE {
+        return None;
+    }
+    return Some(reply);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_x_server;
+
+    const ROOT: Window = test_x_server::ROOT_WINDOW;
+    const FRAME: Window = 0x200;
+
+    /// Cache of the geometries, without selecting events on the root window.
+    fn cache() -> GeometryCache {
+        return GeometryCache {
+            root: ROOT,
+            geometries: HashMap::new(),
+        };
+    }
+
+    fn configure_notify(event: Window, x: i16, width: u16) -> Event {
+        return Event::ConfigureNotify(xproto::ConfigureNotifyEvent {
+            response_type: xproto::CONFIGURE_NOTIFY_EVENT,
+            sequence: 0,
+            event,
+            window: FRAME,
+            above_sibling: x11rb::NONE,
+            x,
+            y: 20,
+            width,
+            height: 300,
+            border_width: 2,
+            override_redirect: false,
+        });
+    }
+
+    fn destroy_notify(event: Window) -> Event {
+        return Event::DestroyNotify(xproto::DestroyNotifyEvent {
+            response_type: xproto::DESTROY_NOTIFY_EVENT,
+            sequence: 0,
+            event,
+            window: FRAME,
+        });
+    }
+
+    #[test]
+    fn configured_geometries_are_cached() {
+        // Any request fails, so only cached geometries are returned.
+        let connection = test_x_server::connect_closing_after(Some(0));
+        let mut cache = cache();
+        assert_eq!(cache.get(&connection, FRAME), None);
+
+        cache.handle_event(&configure_notify(ROOT, 10, 400));
+        let geometry = Geometry {
+            x: 12,
+            y: 22,
+            width: 400,
+            height: 300,
+        };
+        assert_eq!(cache.get(&connection, FRAME), Some(geometry));
+
+        // Moving or resizing the window replaces the geometry.
+        cache.handle_event(&configure_notify(ROOT, 50, 500));
+        let geometry = Geometry {
+            x: 52,
+            y: 22,
+            width: 500,
+            height: 300,
+        };
+        assert_eq!(cache.get(&connection, FRAME), Some(geometry));
+        assert_eq!(cache.get(&connection, x11rb::NONE), None);
+    }
+
+    #[test]
+    fn destroyed_windows_are_forgotten() {
+        let connection = test_x_server::connect_closing_after(Some(0));
+        let mut cache = cache();
+        cache.handle_event(&configure_notify(ROOT, 10, 400));
+        assert!(cache.get(&connection, FRAME).is_some());
+
+        cache.handle_event(&destroy_notify(ROOT));
+        assert_eq!(cache.get(&connection, FRAME), None);
+    }
+
+    #[test]
+    fn events_of_other_windows_are_ignored() {
+        let connection = test_x_server::connect_closing_after(Some(0));
+        let mut cache = cache();
+        cache.handle_event(&configure_notify(FRAME, 10, 400));
+        assert_eq!(cache.get(&connection, FRAME), None);
+
+        cache.handle_event(&configure_notify(ROOT, 10, 400));
+        cache.handle_event(&destroy_notify(FRAME));
+        assert!(cache.get(&connection, FRAME).is_some());
+    }
+}
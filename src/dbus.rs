@@ -0,0 +1,291 @@
+/**
+ * This module exports the application on the session bus, so that desktop
+ * components can query and control it.
+ *
+ * The `org.x11sentinel.Client1` interface is served at `/org/x11sentinel/Client`
+ * under the `org.x11sentinel.Client` name. Its signals are emitted by the
+ * `status` and `data_collector` modules via the functions of this module.
+ */
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use zbus::blocking::{Connection, ConnectionBuilder};
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{dbus_interface, fdo, SignalContext};
+
+use crate::data_collector;
+use crate::status;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+const BUS_NAME: &str = "org.x11sentinel.Client";
+const OBJECT_PATH: &str = "/org/x11sentinel/Client";
+
+//==============================================================================
+// Statics
+//==============================================================================
+
+/// Connection to the session bus, set once the service is started.
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+struct Service;
+
+#[dbus_interface(name = "org.x11sentinel.Client1")]
+impl Service {
+    /// Pause the collection of input events.
+    fn pause(&self) -> () {
+        data_collector::pause();
+    }
+
+    /// Resume the collection of input events.
+    fn resume(&self) -> () {
+        data_collector::resume();
+    }
+
+    /// Submit the buffered events right away.
+    fn flush(&self) -> fdo::Result<()> {
+        return data_collector::flush().map_err(fdo::Error::Failed);
+    }
+
+    /// Return the state of the data collection and the last status. The
+    /// status fields are missing until the first status is received.
+    fn get_status(&self) -> fdo::Result<HashMap<String, OwnedValue>> {
+        let state = data_collector::query_state().map_err(fdo::Error::Failed)?;
+        let mut result = HashMap::new();
+        result.insert(String::from("paused"), Value::from(state.paused).into());
+        result.insert(
+            String::from("bufferSize"),
+            Value::from(state.buffer_size as u64).into(),
+        );
+        result.insert(
+            String::from("sequenceNumber"),
+            Value::from(state.sequence_number).into(),
+        );
+        if let Some(status) = status::last_status() {
+            result.insert(
+                String::from("phase"),
+                Value::from(status.phase.to_string()).into(),
+            );
+            result.insert(
+                String::from("description"),
+                Value::from(status.description).into(),
+            );
+            result.insert(String::from("score"), Value::from(status.value).into());
+        }
+        return Ok(result);
+    }
+
+    /// Emitted when a status is received.
+    #[dbus_interface(signal)]
+    async fn status_changed(
+        context: &SignalContext<'_>,
+        phase: &str,
+        description: &str,
+        score: f64,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when the phase changes. The previous phase is empty for the
+    /// first status.
+    #[dbus_interface(signal)]
+    async fn phase_changed(context: &SignalContext<'_>, from: &str, to: &str) -> zbus::Result<()>;
+
+    /// Emitted when the application locks the session.
+    #[dbus_interface(signal)]
+    async fn lock_triggered(
+        context: &SignalContext<'_>,
+        phase: &str,
+        score: f64,
+        threshold: f64,
+    ) -> zbus::Result<()>;
+
+    /// Emitted when a chunk could not be submitted.
+    #[dbus_interface(signal)]
+    async fn submit_failed(
+        context: &SignalContext<'_>,
+        sequence_number: u64,
+        error: &str,
+    ) -> zbus::Result<()>;
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Export the service on the session bus. The application keeps running
+/// without the service if the session bus is unavailable.
+pub fn start() -> () {
+    serve(ConnectionBuilder::session());
+}
+
+/// Emit the `StatusChanged` signal.
+pub fn emit_status_changed(phase: &str, description: &str, score: f64) -> () {
+    if let Some(context) = get_signal_context() {
+        report(zbus::block_on(Service::status_changed(
+            &context,
+            phase,
+            description,
+            score,
+        )));
+    }
+}
+
+/// Emit the `PhaseChanged` signal.
+pub fn emit_phase_changed(from: &str, to: &str) -> () {
+    if let Some(context) = get_signal_context() {
+        report(zbus::block_on(Service::phase_changed(&context, from, to)));
+    }
+}
+
+/// Emit the `LockTriggered` signal.
+pub fn emit_lock_triggered(phase: &str, score: f64, threshold: f64) -> () {
+    if let Some(context) = get_signal_context() {
+        report(zbus::block_on(Service::lock_triggered(
+            &context, phase, score, threshold,
+        )));
+    }
+}
+
+/// Emit the `SubmitFailed` signal.
+pub fn emit_submit_failed(sequence_number: u64, error: &str) -> () {
+    if let Some(context) = get_signal_context() {
+        report(zbus::block_on(Service::submit_failed(
+            &context,
+            sequence_number,
+            error,
+        )));
+    }
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Export the service on the bus the builder connects to, see `start`.
+fn serve(builder: zbus::Result<ConnectionBuilder<'static>>) -> () {
+    let connection = builder
+        .and_then(|builder| builder.name(BUS_NAME))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, Service))
+        .and_then(|builder| builder.build());
+    match connection {
+        Ok(connection) => {
+            let _ = CONNECTION.set(connection);
+        }
+        Err(error) => println!("Could not start D-Bus service: {}", error),
+    }
+}
+
+/// Get the context for emitting signals, `None` if the service is not
+/// running.
+fn get_signal_context() -> Option<SignalContext<'static>> {
+    let connection = CONNECTION.get()?;
+    match SignalContext::new(connection.inner(), OBJECT_PATH) {
+        Ok(context) => return Some(context),
+        Err(error) => {
+            println!("Could not emit D-Bus signal: {}", error);
+            return None;
+        }
+    }
+}
+
+/// Report the failure of emitting a signal.
+fn report(result: zbus::Result<()>) -> () {
+    match result {
+        Ok(()) => (),
+        Err(error) => println!("Could not emit D-Bus signal: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use zbus::dbus_proxy;
+
+    use crate::test_bus::TestBus;
+
+    #[dbus_proxy(
+        interface = "org.x11sentinel.Client1",
+        default_service = "org.x11sentinel.Client",
+        default_path = "/org/x11sentinel/Client"
+    )]
+    trait Client {
+        fn pause(&self) -> zbus::Result<()>;
+
+        fn resume(&self) -> zbus::Result<()>;
+
+        fn get_status(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+        #[dbus_proxy(signal)]
+        fn status_changed(&self, phase: &str, description: &str, score: f64) -> zbus::Result<()>;
+    }
+
+    /// Resumes the collection when dropped, so that a failing test does not
+    /// leave the shared pause state behind.
+    struct ResumeOnDrop;
+
+    impl Drop for ResumeOnDrop {
+        fn drop(&mut self) {
+            data_collector::resume();
+        }
+    }
+
+    #[test]
+    fn service_is_controlled_over_session_bus() {
+        let bus = match TestBus::start() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let _channel = data_collector::TEST_CHANNEL_LOCK
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let _resume = ResumeOnDrop;
+        data_collector::resume();
+        data_collector::serve_test_state(3, 42);
+        serve(Ok(bus.builder()));
+
+        let connection = bus.connect();
+        let client = ClientProxyBlocking::new(&connection).unwrap();
+
+        let state = client.get_status().unwrap();
+        assert_eq!(state["paused"], Value::from(false).into());
+        assert_eq!(state["bufferSize"], Value::from(3u64).into());
+        assert_eq!(state["sequenceNumber"], Value::from(42u64).into());
+        assert!(!state.contains_key("phase"));
+
+        client.pause().unwrap();
+        assert!(data_collector::is_paused());
+        assert_eq!(
+            client.get_status().unwrap()["paused"],
+            Value::from(true).into()
+        );
+        client.resume().unwrap();
+        assert!(!data_collector::is_paused());
+
+        // The subscription is in place once the signal stream is returned.
+        let signals = client.receive_status_changed().unwrap();
+        let (signal_tx, signal_rx) = mpsc::channel();
+        thread::spawn(move || {
+            for signal in signals {
+                let args = signal.args().unwrap();
+                let _ = signal_tx.send((
+                    String::from(*args.phase()),
+                    String::from(*args.description()),
+                    *args.score(),
+                ));
+            }
+        });
+        emit_status_changed("Verify", "Looks good", 0.75);
+        let signal = signal_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            signal,
+            (String::from("Verify"), String::from("Looks good"), 0.75)
+        );
+    }
+}
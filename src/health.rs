@@ -0,0 +1,227 @@
+/**
+ * This module keeps track of the liveness of the application's subsystems and
+ * reports it on a local HTTP endpoint, so that a supervisor can restart the
+ * application when a subsystem died.
+ *
+ * A subsystem is alive while the thread running it holds its `Liveness`
+ * guard. The guard is dropped when the thread returns or panics.
+ */
+use serde_json::json;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Response, Server};
+
+use crate::control;
+
+//==============================================================================
+// Statics
+//==============================================================================
+
+/// Whether the subsystems are alive, indexed by `Subsystem`.
+static ALIVE: [AtomicBool; 3] = [
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+    AtomicBool::new(false),
+];
+
+/// Milliseconds since 00:00:00 UTC 1 January 1970 of the last pushed event, 0
+/// if there was none yet.
+static LAST_EVENT: AtomicU64 = AtomicU64::new(0);
+
+/// Milliseconds since 00:00:00 UTC 1 January 1970 of the last successful
+/// submission, 0 if there was none yet.
+static LAST_SUBMIT: AtomicU64 = AtomicU64::new(0);
+
+/// Milliseconds since 00:00:00 UTC 1 January 1970 of the last received status,
+/// 0 if there was none yet.
+static LAST_STATUS_POLL: AtomicU64 = AtomicU64::new(0);
+
+/// Milliseconds since 00:00:00 UTC 1 January 1970 by which the status thread
+/// is expected to make progress, 0 if none is expected.
+static STATUS_DEADLINE: AtomicU64 = AtomicU64::new(0);
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+#[derive(Clone, Copy, Debug)]
+pub enum Subsystem {
+    /// Thread receiving the events from the X server.
+    Collector,
+    /// Main event loop buffering and submitting the events.
+    Submitter,
+    /// Thread polling the status of the client.
+    Status,
+}
+
+impl Subsystem {
+    const ALL: [Subsystem; 3] = [
+        Subsystem::Collector,
+        Subsystem::Submitter,
+        Subsystem::Status,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Subsystem::Collector => "collector",
+            Subsystem::Submitter => "submitter",
+            Subsystem::Status => "status",
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        return ALIVE[*self as usize].load(Ordering::SeqCst);
+    }
+}
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Guard marking a subsystem alive until it is dropped.
+pub struct Liveness {
+    subsystem: Subsystem,
+}
+
+impl Drop for Liveness {
+    fn drop(&mut self) {
+        ALIVE[self.subsystem as usize].store(false, Ordering::SeqCst);
+        println!("The {} subsystem stopped", self.subsystem.name());
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Mark the subsystem alive until the returned guard is dropped.
+pub fn start(subsystem: Subsystem) -> Liveness {
+    ALIVE[subsystem as usize].store(true, Ordering::SeqCst);
+    return Liveness { subsystem };
+}
+
+/// Record that an event was pushed to the event buffer.
+pub fn record_event() -> () {
+    LAST_EVENT.store(now(), Ordering::SeqCst);
+}
+
+/// Record that a chunk was submitted successfully.
+pub fn record_submit() -> () {
+    LAST_SUBMIT.store(now(), Ordering::SeqCst);
+}
+
+/// Record that a status was received.
+pub fn record_status_poll() -> () {
+    LAST_STATUS_POLL.store(now(), Ordering::SeqCst);
+}
+
+/// Expect the status thread to make progress within the given time, i.e. to
+/// receive a status, to see the push channel connected or to fail a status
+/// request, and to call this function again.
+pub fn expect_status_within(timeout: Duration) -> () {
+    let timeout: u64 = timeout.as_millis().try_into().unwrap_or(u64::MAX);
+    STATUS_DEADLINE.store(now().saturating_add(timeout), Ordering::SeqCst);
+}
+
+/// Whether every subsystem is alive and the status thread is not stuck.
+pub fn is_ready() -> bool {
+    return Subsystem::ALL.iter().all(Subsystem::is_alive) && !is_status_overdue();
+}
+
+/// Serve the health report on the given address in a background thread. The
+/// address is either a TCP address or the path of a Unix domain socket.
+/// Nothing is served if the address is empty.
+pub fn serve(address: &str) -> () {
+    if address.is_empty() {
+        return;
+    }
+    let server = match address.starts_with('/') {
+        true => {
+            if let Err(error) = control::remove_stale_socket(Path::new(address)) {
+                println!("Not serving the health report: {}", error);
+                return;
+            }
+            Server::http_unix(Path::new(address))
+        }
+        false => Server::http(address),
+    };
+    let server = match server {
+        Ok(server) => server,
+        Err(error) => {
+            println!("Could not serve health report on {}: {}", address, error);
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = match request.url() {
+                "/health" => {
+                    let status_code = match is_ready() {
+                        true => 200,
+                        false => 503,
+                    };
+                    Response::from_string(report().to_string())
+                        .with_status_code(status_code)
+                        .with_header(
+                            Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                                .unwrap(),
+                        )
+                }
+                _ => Response::from_string("Not Found").with_status_code(404),
+            };
+            match request.respond(response) {
+                Ok(()) => (),
+                Err(error) => println!("Could not send health report: {}", error),
+            }
+        }
+    });
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Create the health report of the application.
+fn report() -> serde_json::Value {
+    let subsystems: serde_json::Map<String, serde_json::Value> = Subsystem::ALL
+        .iter()
+        .map(|subsystem| (subsystem.name().to_string(), json!(subsystem.is_alive())))
+        .collect();
+    return json!({
+        "ready": is_ready(),
+        "subsystems": subsystems,
+        "lastEvent": get_time(&LAST_EVENT),
+        "lastSubmit": get_time(&LAST_SUBMIT),
+        "lastStatusPoll": get_time(&LAST_STATUS_POLL),
+        "statusOverdue": is_status_overdue(),
+    });
+}
+
+/// Whether the status thread did not make progress in time.
+fn is_status_overdue() -> bool {
+    match STATUS_DEADLINE.load(Ordering::SeqCst) {
+        0 => return false,
+        deadline => return now() > deadline,
+    }
+}
+
+/// Get a recorded time, `None` if it was never recorded.
+fn get_time(time: &AtomicU64) -> Option<u64> {
+    match time.load(Ordering::SeqCst) {
+        0 => return None,
+        time => return Some(time),
+    }
+}
+
+/// Return milliseconds since 00:00:00 UTC 1 January 1970
+fn now() -> u64 {
+    return SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        .try_into()
+        .unwrap();
+}
@@ -1,19 +1,69 @@
+// The code base spells out `return` statements and `-> ()` return types.
+#![allow(clippy::needless_return, clippy::unused_unit)]
+
+use std::process;
 use std::thread;
 use uuid::Uuid;
 
 mod config;
+mod control;
 mod data_collector;
+mod dbus;
+mod health;
+mod metrics;
+mod session;
 mod status;
+mod systemd;
+#[cfg(test)]
+mod test_bus;
+#[cfg(test)]
+mod test_x_server;
 
 fn main() {
     // Parse command line arguments and create application configuration
     let config = config::Config::new();
+
+    // Control the running instance instead of starting a new one
+    if let Some(config::Command::Ctl { action }) = config.command {
+        control::run_client(&config.control_socket.unwrap(), action);
+        return;
+    }
+
+    // Initialize the settings shared with the status thread before it starts
+    data_collector::set_buffer_size_limit(config.buffer_size_limit.unwrap());
+    data_collector::set_idle_timeout(config.idle_timeout.unwrap());
+
+    // Refuse to start with settings that cannot be applied
+    let checked = data_collector::check_exclusion(&config)
+        .and_then(|()| status::check_lock_backend(&config))
+        .and_then(|()| status::check_lock_policy(&config))
+        .and_then(|()| status::check_reauth_command(&config))
+        .and_then(|()| status::check_notification(&config));
+    if let Err(error) = checked {
+        eprintln!("Error: {}", error);
+        process::exit(1);
+    }
+
     let config2 = config.clone();
 
     // Generate unique stream identifier.
     let stream_id = Uuid::new_v4().to_string();
     let stream_id2 = stream_id.clone();
 
+    // Serve the control socket if enabled
+    control::serve(&config.control_socket.clone().unwrap());
+
+    // Export the application on the session bus if enabled
+    if config.dbus_service_enabled.unwrap() {
+        dbus::start();
+    }
+
+    // Serve the health report if enabled
+    health::serve(&config.health_address.clone().unwrap());
+
+    // Serve the internal metrics if enabled
+    metrics::serve(&config.metrics_address.clone().unwrap());
+
     // Start the status polling service
     thread::spawn(move || {
         status::run(config, stream_id);
@@ -0,0 +1,155 @@
+/**
+ * This module collects the internal metrics of the application and exposes
+ * them in the Prometheus text format on a local HTTP endpoint.
+ */
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge, register_histogram, register_int_counter, register_int_counter_vec,
+    register_int_gauge, register_int_gauge_vec, Encoder, Gauge, Histogram, IntCounter,
+    IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+};
+use std::thread;
+use tiny_http::{Header, Response, Server};
+
+//==============================================================================
+// Statics
+//==============================================================================
+
+lazy_static! {
+    /// Number of events pushed to the event buffer per event type.
+    pub static ref EVENTS: IntCounterVec = register_int_counter_vec!(
+        "x11_sentinel_events_total",
+        "Number of events pushed to the event buffer.",
+        &["type"]
+    )
+    .unwrap();
+
+    /// Number of messages waiting in the data collector's channel.
+    pub static ref CHANNEL_BACKLOG: IntGauge = register_int_gauge!(
+        "x11_sentinel_channel_backlog",
+        "Number of messages waiting in the data collector's channel."
+    )
+    .unwrap();
+
+    /// Number of chunks submitted successfully.
+    pub static ref CHUNKS_SENT: IntCounter = register_int_counter!(
+        "x11_sentinel_chunks_sent_total",
+        "Number of chunks submitted successfully."
+    )
+    .unwrap();
+
+    /// Number of chunks whose submission failed.
+    pub static ref CHUNKS_FAILED: IntCounter = register_int_counter!(
+        "x11_sentinel_chunks_failed_total",
+        "Number of chunks whose submission failed."
+    )
+    .unwrap();
+
+    /// Number of request body bytes of the submitted chunks.
+    pub static ref BYTES_UPLOADED: IntCounter = register_int_counter!(
+        "x11_sentinel_uploaded_bytes_total",
+        "Number of request body bytes of the submitted chunks."
+    )
+    .unwrap();
+
+    /// Duration of the chunk submissions.
+    pub static ref SUBMIT_DURATION: Histogram = register_histogram!(
+        "x11_sentinel_submit_duration_seconds",
+        "Duration of the chunk submissions."
+    )
+    .unwrap();
+
+    /// Duration of the status requests.
+    pub static ref STATUS_POLL_DURATION: Histogram = register_histogram!(
+        "x11_sentinel_status_poll_duration_seconds",
+        "Duration of the status requests."
+    )
+    .unwrap();
+
+    /// Number of status requests that failed.
+    pub static ref STATUS_POLLS_FAILED: IntCounter = register_int_counter!(
+        "x11_sentinel_status_polls_failed_total",
+        "Number of status requests that failed."
+    )
+    .unwrap();
+
+    /// Unix time of the last status received from the remote server.
+    pub static ref LAST_STATUS_TIME: IntGauge = register_int_gauge!(
+        "x11_sentinel_last_status_timestamp_seconds",
+        "Unix time of the last status received from the remote server."
+    )
+    .unwrap();
+
+    /// The user's last score.
+    pub static ref SCORE: Gauge = register_gauge!(
+        "x11_sentinel_score",
+        "The user's last score."
+    )
+    .unwrap();
+
+    /// The current phase, labeled with the phase and set to 1.
+    pub static ref PHASE: IntGaugeVec = register_int_gauge_vec!(
+        "x11_sentinel_phase",
+        "The current phase of the user's profile.",
+        &["phase"]
+    )
+    .unwrap();
+
+    /// Number of session locks triggered by the application.
+    pub static ref LOCKS: IntCounter = register_int_counter!(
+        "x11_sentinel_locks_total",
+        "Number of session locks triggered by the application."
+    )
+    .unwrap();
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Serve the metrics on the given address in a background thread. Nothing is
+/// served if the address is empty.
+pub fn serve(address: &str) -> () {
+    if address.is_empty() {
+        return;
+    }
+    let server = match Server::http(address) {
+        Ok(server) => server,
+        Err(error) => {
+            println!("Could not serve metrics on {}: {}", address, error);
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = match request.url() {
+                "/metrics" => Response::from_data(encode()).with_header(content_type()),
+                _ => Response::from_string("Not Found").with_status_code(404),
+            };
+            match request.respond(response) {
+                Ok(()) => (),
+                Err(error) => println!("Could not send metrics: {}", error),
+            }
+        }
+    });
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Encode the registered metrics in the Prometheus text format.
+fn encode() -> Vec<u8> {
+    let mut buffer = Vec::new();
+    match TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        Ok(()) => (),
+        Err(error) => println!("Could not encode metrics: {}", error),
+    }
+    return buffer;
+}
+
+/// Content type of the Prometheus text format.
+fn content_type() -> Header {
+    return Header::from_bytes(&b"Content-Type"[..], TextEncoder::new().format_type()).unwrap();
+}
@@ -0,0 +1,70 @@
+/**
+ * D-Bus proxies for the subset of the systemd-logind API used by the
+ * application.
+ */
+use zbus::dbus_proxy;
+use zbus::zvariant::{OwnedFd, OwnedObjectPath};
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    /// Get the object path of the session with the given identifier.
+    fn get_session(&self, session_id: &str) -> zbus::Result<OwnedObjectPath>;
+
+    /// Get the object path of the session the given process belongs to.
+    #[dbus_proxy(name = "GetSessionByPID")]
+    fn get_session_by_pid(&self, pid: u32) -> zbus::Result<OwnedObjectPath>;
+
+    /// Get the object path of the user with the given uid.
+    fn get_user(&self, uid: u32) -> zbus::Result<OwnedObjectPath>;
+
+    /// Take an inhibitor lock. The lock is released when the returned file
+    /// descriptor is closed.
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    /// Emitted before the system goes to sleep (`start` is true) and after it
+    /// resumed (`start` is false).
+    #[dbus_proxy(signal)]
+    fn prepare_for_sleep(&self, start: bool) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.Session",
+    default_service = "org.freedesktop.login1"
+)]
+trait Session {
+    #[dbus_proxy(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn seat(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+
+    #[dbus_proxy(property, name = "VTNr")]
+    fn vtnr(&self) -> zbus::Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn class(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property, name = "Type")]
+    fn type_(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property)]
+    fn remote(&self) -> zbus::Result<bool>;
+
+    /// Whether the session is locked, as reported by the screen locker.
+    #[dbus_proxy(property)]
+    fn locked_hint(&self) -> zbus::Result<bool>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.login1.User",
+    default_service = "org.freedesktop.login1"
+)]
+trait User {
+    /// The graphical session of the user.
+    #[dbus_proxy(property)]
+    fn display(&self) -> zbus::Result<(String, OwnedObjectPath)>;
+}
@@ -0,0 +1,552 @@
+/**
+ * This module queries the identity of the current graphical session from
+ * systemd-logind over D-Bus.
+ *
+ * The queries take the bus connection as an argument, so a mock logind
+ * service on a private bus can be used instead of the real one.
+ */
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::process;
+use std::thread;
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedFd, OwnedObjectPath};
+
+mod logind;
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SessionEvent {
+    Locked,
+    Unlocked,
+    Suspend,
+    Resume,
+    Idle,
+    Active,
+}
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub id: String,
+    pub seat: Option<String>,
+    pub vt: Option<u32>,
+    pub class: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub remote: Option<bool>,
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Query the current graphical session from logind. If logind is unavailable,
+/// only the session identifier is filled from the environment.
+pub fn query_session() -> Session {
+    return query_session_on(Connection::system());
+}
+
+/// Query whether the current session is locked according to logind. `None`
+/// is returned if logind is unavailable.
+pub fn query_locked() -> Option<bool> {
+    return query_locked_on(Connection::system());
+}
+
+/// Query the current graphical session from the logind service reachable on
+/// the given connection.
+pub fn query_logind_session(connection: &Connection) -> zbus::Result<Session> {
+    let path = get_session_path(connection)?;
+    let proxy = logind::SessionProxyBlocking::builder(connection)
+        .path(path)?
+        .build()?;
+
+    Ok(Session {
+        id: proxy.id()?,
+        seat: Some(proxy.seat()?.0).filter(|seat| !seat.is_empty()),
+        vt: Some(proxy.vtnr()?).filter(|vt| *vt != 0),
+        class: Some(proxy.class()?),
+        type_: Some(proxy.type_()?),
+        remote: Some(proxy.remote()?),
+    })
+}
+
+/// Watch logind for session lock state changes and for the system going to
+/// sleep or resuming. The `callback` is called from background threads for
+/// every change. The system does not go to sleep before the callback of the
+/// suspend event returned, up to the `InhibitDelayMaxSec` of logind. Nothing
+/// is watched if logind is unavailable.
+pub fn watch_session<F>(callback: F) -> ()
+where
+    F: Fn(SessionEvent) + Clone + Send + 'static,
+{
+    let connection = match Connection::system() {
+        Ok(connection) => connection,
+        Err(error) => {
+            println!("Could not connect to the system bus: {}", error);
+            return;
+        }
+    };
+
+    let sleep_connection = connection.clone();
+    let sleep_callback = callback.clone();
+    thread::spawn(
+        move || match watch_sleep(&sleep_connection, sleep_callback) {
+            Ok(()) => (),
+            Err(error) => println!("Could not watch sleep state: {}", error),
+        },
+    );
+
+    thread::spawn(move || match watch_lock(&connection, callback) {
+        Ok(()) => (),
+        Err(error) => println!("Could not watch lock state: {}", error),
+    });
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Query the current graphical session on the given system bus connection,
+/// see `query_session`.
+fn query_session_on(connection: zbus::Result<Connection>) -> Session {
+    let result = connection.and_then(|connection| query_logind_session(&connection));
+    match result {
+        Ok(session) => return session,
+        Err(error) => {
+            println!("Could not query session from logind: {}", error);
+            return Session {
+                id: get_session_id(),
+                ..Session::default()
+            };
+        }
+    }
+}
+
+/// Query whether the current session is locked on the given system bus
+/// connection, see `query_locked`.
+fn query_locked_on(connection: zbus::Result<Connection>) -> Option<bool> {
+    let result = connection.and_then(|connection| {
+        let path = get_session_path(&connection)?;
+        let proxy = logind::SessionProxyBlocking::builder(&connection)
+            .path(path)?
+            .build()?;
+        proxy.locked_hint()
+    });
+    match result {
+        Ok(locked) => return Some(locked),
+        Err(error) => {
+            println!("Could not query lock state from logind: {}", error);
+            return None;
+        }
+    }
+}
+
+/// Report the `PrepareForSleep` signals of logind as suspend and resume
+/// events. A delay inhibitor lock is held while the system is awake, so that
+/// the system goes to sleep only once the callback returned.
+fn watch_sleep<F: Fn(SessionEvent)>(connection: &Connection, callback: F) -> zbus::Result<()> {
+    let manager = logind::ManagerProxyBlocking::new(connection)?;
+    let signals = manager.receive_prepare_for_sleep()?;
+    let mut inhibitor = take_sleep_inhibitor(&manager);
+    for signal in signals {
+        match signal.args()?.start {
+            true => {
+                callback(SessionEvent::Suspend);
+                drop(inhibitor.take());
+            }
+            false => {
+                inhibitor = take_sleep_inhibitor(&manager);
+                callback(SessionEvent::Resume);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Take a logind delay inhibitor lock for sleep. `None` is returned if it is
+/// not granted, the system then goes to sleep without waiting.
+fn take_sleep_inhibitor(manager: &logind::ManagerProxyBlocking) -> Option<OwnedFd> {
+    match manager.inhibit("sleep", "x11-sentinel-client", "flush events", "delay") {
+        Ok(inhibitor) => return Some(inhibitor),
+        Err(error) => {
+            println!("Could not take sleep inhibitor lock: {}", error);
+            return None;
+        }
+    }
+}
+
+/// Report the changes of the `LockedHint` session property as lock and unlock
+/// events.
+fn watch_lock<F: Fn(SessionEvent)>(connection: &Connection, callback: F) -> zbus::Result<()> {
+    let path = get_session_path(connection)?;
+    let proxy = logind::SessionProxyBlocking::builder(connection)
+        .path(path)?
+        .build()?;
+
+    let mut locked = proxy.locked_hint()?;
+    for change in proxy.receive_locked_hint_changed() {
+        let value = change.get()?;
+        if value == locked {
+            continue;
+        }
+        locked = value;
+        match locked {
+            true => callback(SessionEvent::Locked),
+            false => callback(SessionEvent::Unlocked),
+        }
+    }
+    Ok(())
+}
+
+/// Find the object path of the current session. The session is looked up by
+/// `XDG_SESSION_ID` first, then by the process identifier. Processes running
+/// outside of a session (e.g. as a `systemd --user` service) fall back to the
+/// graphical session of the user.
+fn get_session_path(connection: &Connection) -> zbus::Result<OwnedObjectPath> {
+    let manager = logind::ManagerProxyBlocking::new(connection)?;
+
+    match env::var("XDG_SESSION_ID") {
+        Ok(session_id) if !session_id.is_empty() => {
+            return manager.get_session(&session_id);
+        }
+        _ => (),
+    }
+
+    match manager.get_session_by_pid(process::id()) {
+        Ok(path) => return Ok(path),
+        Err(error) => println!("Could not get session by pid: {}", error),
+    }
+
+    let user_path = manager.get_user(unsafe { libc::getuid() })?;
+    let user = logind::UserProxyBlocking::builder(connection)
+        .path(user_path)?
+        .build()?;
+    let (_session_id, session_path) = user.display()?;
+    return Ok(session_path);
+}
+
+/// Get the unique identifier of the current user session. The identifier is
+/// read from the `XDG_SESSION_ID` environment variable set by logind, falling
+/// back to the audit session identifier of the process. An empty string is
+/// returned if neither of them is available.
+fn get_session_id() -> String {
+    return choose_session_id(
+        env::var("XDG_SESSION_ID").ok(),
+        fs::read_to_string("/proc/self/sessionid"),
+    );
+}
+
+/// Choose the session identifier from the value of `XDG_SESSION_ID` and the
+/// content of `/proc/self/sessionid`.
+fn choose_session_id(
+    xdg_session_id: Option<String>,
+    audit_session_id: std::io::Result<String>,
+) -> String {
+    match xdg_session_id {
+        Some(value) if !value.is_empty() => return value,
+        _ => (),
+    }
+
+    // The kernel reports an unset audit session as `u32::MAX`.
+    match audit_session_id {
+        Ok(value) if value.trim() != u32::MAX.to_string() => {
+            return String::from(value.trim());
+        }
+        Ok(_value) => (),
+        Err(error) => println!("Could not read session id: {}", error),
+    }
+
+    return String::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::io::Read;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    use std::os::unix::net::UnixStream;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::time::Duration;
+    use zbus::{dbus_interface, fdo, SignalContext};
+
+    use crate::test_bus::TestBus;
+
+    const SESSION_PATH: &str = "/org/freedesktop/login1/session/c1";
+
+    /// Minimal `org.freedesktop.login1.Manager`. Every lookup resolves to the
+    /// same session, whichever `XDG_SESSION_ID` the tests run with. The
+    /// arguments of the inhibitor requests are sent to `inhibits` along with
+    /// the peer of the returned file descriptor.
+    #[derive(Default)]
+    struct MockManager {
+        inhibits: Option<mpsc::Sender<(String, UnixStream)>>,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.login1.Manager")]
+    impl MockManager {
+        fn get_session(&self, _session_id: &str) -> OwnedObjectPath {
+            return OwnedObjectPath::try_from(SESSION_PATH).unwrap();
+        }
+
+        #[dbus_interface(name = "GetSessionByPID")]
+        fn get_session_by_pid(&self, _pid: u32) -> OwnedObjectPath {
+            return OwnedObjectPath::try_from(SESSION_PATH).unwrap();
+        }
+
+        fn get_user(&self, _uid: u32) -> fdo::Result<OwnedObjectPath> {
+            return Err(fdo::Error::Failed(String::from("no such user")));
+        }
+
+        fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> fdo::Result<OwnedFd> {
+            let (local, remote) =
+                UnixStream::pair().map_err(|error| fdo::Error::Failed(error.to_string()))?;
+            if let Some(inhibits) = &self.inhibits {
+                let _ = inhibits.send((format!("{} {} {} {}", what, who, why, mode), local));
+            }
+            return Ok(unsafe { OwnedFd::from_raw_fd(remote.into_raw_fd()) });
+        }
+
+        #[dbus_interface(signal)]
+        async fn prepare_for_sleep(context: &SignalContext<'_>, start: bool) -> zbus::Result<()>;
+    }
+
+    /// Minimal `org.freedesktop.login1.Session`. Counts the reads of the
+    /// `LockedHint`.
+    #[derive(Default)]
+    struct MockSession {
+        locked_hint: bool,
+        locked_hint_reads: Arc<AtomicUsize>,
+    }
+
+    #[dbus_interface(name = "org.freedesktop.login1.Session")]
+    impl MockSession {
+        #[dbus_interface(property)]
+        fn id(&self) -> String {
+            return String::from("c1");
+        }
+
+        #[dbus_interface(property)]
+        fn seat(&self) -> (String, OwnedObjectPath) {
+            let path = OwnedObjectPath::try_from("/org/freedesktop/login1/seat/seat0").unwrap();
+            return (String::from("seat0"), path);
+        }
+
+        #[dbus_interface(property, name = "VTNr")]
+        fn vtnr(&self) -> u32 {
+            return 2;
+        }
+
+        #[dbus_interface(property)]
+        fn class(&self) -> String {
+            return String::from("user");
+        }
+
+        #[dbus_interface(property, name = "Type")]
+        fn type_(&self) -> String {
+            return String::from("x11");
+        }
+
+        #[dbus_interface(property)]
+        fn remote(&self) -> bool {
+            return false;
+        }
+
+        #[dbus_interface(property)]
+        fn locked_hint(&self) -> bool {
+            self.locked_hint_reads.fetch_add(1, Ordering::SeqCst);
+            return self.locked_hint;
+        }
+    }
+
+    /// Serve the mock logind service on the bus.
+    fn serve_logind(bus: &TestBus, manager: MockManager, session: MockSession) -> Connection {
+        return bus
+            .builder()
+            .serve_at("/org/freedesktop/login1", manager)
+            .unwrap()
+            .serve_at(SESSION_PATH, session)
+            .unwrap()
+            .name("org.freedesktop.login1")
+            .unwrap()
+            .build()
+            .unwrap();
+    }
+
+    /// Set the `LockedHint` of the mock session and announce the change.
+    fn set_locked_hint(logind: &Connection, locked: bool) -> () {
+        let session = logind
+            .object_server()
+            .interface::<_, MockSession>(SESSION_PATH)
+            .unwrap();
+        session.get_mut().locked_hint = locked;
+        zbus::block_on(session.get().locked_hint_changed(session.signal_context())).unwrap();
+    }
+
+    /// Change the `LockedHint` until the watcher reports the expected event.
+    /// The watcher may subscribe to the changes after the first attempts.
+    fn expect_lock_event(
+        logind: &Connection,
+        events: &mpsc::Receiver<SessionEvent>,
+        locked: bool,
+        expected: SessionEvent,
+    ) -> () {
+        for _attempt in 0..50 {
+            set_locked_hint(logind, locked);
+            match events.recv_timeout(Duration::from_millis(100)) {
+                Ok(event) => {
+                    assert_eq!(event, expected);
+                    return;
+                }
+                Err(_error) => continue,
+            }
+        }
+        panic!("No {:?} event received", expected);
+    }
+
+    #[test]
+    fn session_is_queried_from_logind() {
+        let bus = match TestBus::start() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let _logind = serve_logind(&bus, MockManager::default(), MockSession::default());
+
+        let session = query_logind_session(&bus.connect()).unwrap();
+        let expected = Session {
+            id: String::from("c1"),
+            seat: Some(String::from("seat0")),
+            vt: Some(2),
+            class: Some(String::from("user")),
+            type_: Some(String::from("x11")),
+            remote: Some(false),
+        };
+        assert_eq!(session, expected);
+
+        assert_eq!(query_session_on(Ok(bus.connect())), expected);
+        assert_eq!(query_locked_on(Ok(bus.connect())), Some(false));
+    }
+
+    #[test]
+    fn missing_logind_is_reported() {
+        let error = || Err(zbus::Error::Unsupported);
+        assert_eq!(query_session_on(error()).id, get_session_id());
+        assert_eq!(query_session_on(error()).seat, None);
+        assert_eq!(query_locked_on(error()), None);
+    }
+
+    #[test]
+    fn lock_changes_are_reported() {
+        let bus = match TestBus::start() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let locked_hint_reads = Arc::new(AtomicUsize::new(0));
+        let session = MockSession {
+            locked_hint: false,
+            locked_hint_reads: locked_hint_reads.clone(),
+        };
+        let logind = serve_logind(&bus, MockManager::default(), session);
+
+        let (events_tx, events) = mpsc::channel();
+        let connection = bus.connect();
+        std::thread::spawn(move || {
+            let _ = watch_lock(&connection, move |event| {
+                let _ = events_tx.send(event);
+            });
+        });
+
+        // Lock the session only once the watcher read the initial state.
+        while locked_hint_reads.load(Ordering::SeqCst) == 0 {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        expect_lock_event(&logind, &events, true, SessionEvent::Locked);
+        expect_lock_event(&logind, &events, false, SessionEvent::Unlocked);
+
+        // Announcing an unchanged state is not reported again.
+        set_locked_hint(&logind, false);
+        assert!(events.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn sleep_is_delayed_until_suspend_is_handled() {
+        let bus = match TestBus::start() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let (inhibits_tx, inhibits) = mpsc::channel();
+        let manager = MockManager {
+            inhibits: Some(inhibits_tx),
+        };
+        let logind = serve_logind(&bus, manager, MockSession::default());
+        let prepare_for_sleep = |start: bool| {
+            let manager = logind
+                .object_server()
+                .interface::<_, MockManager>("/org/freedesktop/login1")
+                .unwrap();
+            zbus::block_on(MockManager::prepare_for_sleep(
+                manager.signal_context(),
+                start,
+            ))
+            .unwrap();
+        };
+
+        let (events_tx, events) = mpsc::channel();
+        let connection = bus.connect();
+        std::thread::spawn(move || {
+            let _ = watch_sleep(&connection, move |event| {
+                let _ = events_tx.send(event);
+                // Pretend that submitting the buffer takes a while.
+                std::thread::sleep(Duration::from_millis(200));
+            });
+        });
+
+        // The inhibitor lock is taken at startup.
+        let timeout = Duration::from_secs(5);
+        let (arguments, mut inhibitor) = inhibits.recv_timeout(timeout).unwrap();
+        assert_eq!(arguments, "sleep x11-sentinel-client flush events delay");
+
+        // It is held while the suspend event is handled and released after.
+        prepare_for_sleep(true);
+        assert_eq!(events.recv_timeout(timeout), Ok(SessionEvent::Suspend));
+        inhibitor
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+        assert!(inhibitor.read(&mut [0]).is_err());
+        inhibitor.set_read_timeout(Some(timeout)).unwrap();
+        assert_eq!(inhibitor.read(&mut [0]).unwrap(), 0);
+
+        // It is taken again after resuming.
+        prepare_for_sleep(false);
+        assert_eq!(events.recv_timeout(timeout), Ok(SessionEvent::Resume));
+        let (arguments, _inhibitor) = inhibits.recv_timeout(timeout).unwrap();
+        assert_eq!(arguments, "sleep x11-sentinel-client flush events delay");
+    }
+
+    #[test]
+    fn session_id_falls_back_to_audit_session() {
+        let audit = |value: &str| Ok(String::from(value));
+        let missing = || Err(io::Error::from(io::ErrorKind::NotFound));
+
+        assert_eq!(
+            choose_session_id(Some(String::from("c2")), audit("3\n")),
+            "c2"
+        );
+        assert_eq!(choose_session_id(None, audit("3\n")), "3");
+        assert_eq!(choose_session_id(Some(String::new()), audit("3")), "3");
+        assert_eq!(choose_session_id(None, audit("4294967295")), "");
+        assert_eq!(choose_session_id(None, missing()), "");
+        assert_eq!(choose_session_id(Some(String::from("c2")), missing()), "c2");
+    }
+}
@@ -0,0 +1,324 @@
+/**
+ * This module implements the session lock backends and keeps track of the
+ * session locks triggered by the application.
+ */
+use chrono::{DateTime, Duration, Local};
+use std::process::{Child, Command};
+use zbus::blocking::Connection;
+
+use crate::dbus;
+use crate::metrics;
+use crate::session;
+use crate::status::phase::Phase;
+use crate::status::Status;
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+/// Backend used to lock the session.
+#[derive(Clone, Debug)]
+pub enum Backend {
+    /// Execute a command. The arguments may contain placeholders for the values
+    /// that triggered the lock.
+    Command(String, Vec<String>),
+    /// Execute `loginctl lock-session`.
+    Loginctl,
+    /// Call `org.freedesktop.ScreenSaver.Lock` on the session bus.
+    DBus,
+    /// Execute `xdg-screensaver lock`.
+    XdgScreensaver,
+}
+
+impl Backend {
+    /// Constructor for the `Backend` object. Unknown backend names fall back
+    /// to the `command` backend. The arguments are split like a shell does,
+    /// an error is returned if they are not properly quoted.
+    pub fn new(name: &str, lock_utility: String, lock_arguments: &str) -> Result<Backend, String> {
+        match name {
+            "loginctl" => return Ok(Backend::Loginctl),
+            "dbus" => return Ok(Backend::DBus),
+            "xdg-screensaver" => return Ok(Backend::XdgScreensaver),
+            "command" => (),
+            _ => return Err(format!("unknown lock backend: {}", name)),
+        }
+        let arguments = match shell_words::split(lock_arguments) {
+            Ok(arguments) => arguments,
+            Err(error) => return Err(format!("invalid lock arguments: {}", error)),
+        };
+        return Ok(Backend::Command(lock_utility, arguments));
+    }
+
+    /// Lock the session. The spawned process is returned for the backends
+    /// executing a program.
+    fn lock(&self, status: &Status, threshold: f64) -> Result<Option<Child>, String> {
+        let child = match self {
+            Backend::Command(program, arguments) => Command::new(program)
+                .args(arguments.iter().map(|argument| {
+                    argument
+                        .replace("{phase}", &status.phase.to_string())
+                        .replace("{description}", &status.description)
+                        .replace("{score}", &status.value.to_string())
+                        .replace("{threshold}", &threshold.to_string())
+                }))
+                .spawn(),
+            Backend::Loginctl => Command::new("loginctl").arg("lock-session").spawn(),
+            Backend::XdgScreensaver => Command::new("xdg-screensaver").arg("lock").spawn(),
+            Backend::DBus => {
+                return lock_screen_saver()
+                    .map(|()| None)
+                    .map_err(|error| error.to_string())
+            }
+        };
+        return child.map(Some).map_err(|error| error.to_string());
+    }
+}
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Record of a session lock triggered by the application.
+#[derive(Clone, Debug)]
+pub struct LockRecord {
+    pub time: DateTime<Local>,
+    pub phase: Phase,
+    pub description: String,
+    pub value: f64,
+    pub threshold: f64,
+}
+
+pub struct Locker {
+    backend: Backend,
+    lock_threshold: f64,
+    lock_cooldown: u64,
+    child: Option<Child>,
+    last_lock: Option<LockRecord>,
+}
+
+impl Locker {
+    /// Constructor for the `Locker` object. The cooldown is given in seconds.
+    pub fn new(backend: Backend, lock_threshold: f64, lock_cooldown: u64) -> Locker {
+        Locker {
+            backend,
+            lock_threshold,
+            lock_cooldown,
+            child: None,
+            last_lock: None,
+        }
+    }
+
+    /// Lock the session because of the given status and return whether it
+    /// was locked. Nothing happens if the session is already locked, the
+    /// cooldown period has not elapsed since the last lock or the backend
+    /// failed.
+    pub fn lock(&mut self, status: &Status) -> bool {
+        if let Some(record) = &self.last_lock {
+            if Local::now() - record.time < Duration::seconds(self.lock_cooldown as i64) {
+                return false;
+            }
+        }
+
+        if self.is_locked() {
+            return false;
+        }
+
+        match self.backend.lock(status, self.lock_threshold) {
+            Ok(child) => self.child = child,
+            Err(error) => {
+                println!("Could not lock session: {}", error);
+                return false;
+            }
+        }
+
+        let record = LockRecord {
+            time: Local::now(),
+            phase: status.phase.clone(),
+            description: status.description.clone(),
+            value: status.value,
+            threshold: self.lock_threshold,
+        };
+        metrics::LOCKS.inc();
+        dbus::emit_lock_triggered(&record.phase.to_string(), record.value, record.threshold);
+        println!(
+            "Session locked at {}: score {} is below {} (phase: {}, description: {})",
+            record.time, record.value, record.threshold, record.phase, record.description
+        );
+        self.last_lock = Some(record);
+        return true;
+    }
+
+    /// Whether the session is locked, either by the lock utility started by
+    /// the application or by anything else according to logind or the screen
+    /// saver of the desktop environment. Only the `command` backend is
+    /// tracked by the application itself, the locks of the other backends are
+    /// only detected if the screen locker reports them to logind or to the
+    /// `org.freedesktop.ScreenSaver` interface.
+    pub fn is_locked(&mut self) -> bool {
+        if self.reap() {
+            return true;
+        }
+        if session::query_locked() == Some(true) {
+            return true;
+        }
+        let result =
+            Connection::session().and_then(|connection| is_screen_saver_active(&connection));
+        match result {
+            Ok(active) => return active,
+            Err(error) => {
+                println!("Could not query screen saver state: {}", error);
+                return false;
+            }
+        }
+    }
+
+    /// Reap the lock utility once it exited. Return whether it is still
+    /// running.
+    pub fn reap(&mut self) -> bool {
+        let child = match &mut self.child {
+            Some(child) => child,
+            None => return false,
+        };
+        match child.try_wait() {
+            Ok(None) => return true,
+            Ok(Some(exit_status)) => {
+                println!("Lock utility exited: {}", exit_status);
+                self.child = None;
+                return false;
+            }
+            Err(error) => {
+                println!("Could not wait for lock utility: {}", error);
+                return true;
+            }
+        }
+    }
+
+    /// Change the lock threshold recorded with the locks.
+    pub fn set_lock_threshold(&mut self, lock_threshold: f64) -> () {
+        self.lock_threshold = lock_threshold;
+    }
+
+    /// Return the record of the last lock triggered by the application.
+    pub fn last_lock(&self) -> Option<&LockRecord> {
+        return self.last_lock.as_ref();
+    }
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Whether the screen saver reachable on the given connection is active. The
+/// desktop environments implementing `org.freedesktop.ScreenSaver` report
+/// the locked screen as active.
+fn is_screen_saver_active(connection: &Connection) -> zbus::Result<bool> {
+    let reply = connection.call_method(
+        Some("org.freedesktop.ScreenSaver"),
+        "/org/freedesktop/ScreenSaver",
+        Some("org.freedesktop.ScreenSaver"),
+        "GetActive",
+        &(),
+    )?;
+    return reply.body::<bool>();
+}
+
+/// Lock the screen via the `org.freedesktop.ScreenSaver` interface on the
+/// session bus.
+fn lock_screen_saver() -> zbus::Result<()> {
+    let connection = Connection::session()?;
+    connection.call_method(
+        Some("org.freedesktop.ScreenSaver"),
+        "/org/freedesktop/ScreenSaver",
+        Some("org.freedesktop.ScreenSaver"),
+        "Lock",
+        &(),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_bus::TestBus;
+
+    fn get_arguments(lock_arguments: &str) -> Result<Vec<String>, String> {
+        match Backend::new("command", String::from("i3lock"), lock_arguments)? {
+            Backend::Command(_program, arguments) => return Ok(arguments),
+            backend => panic!("Unexpected backend {:?}", backend),
+        }
+    }
+
+    #[test]
+    fn lock_arguments_are_split_like_a_shell() {
+        assert_eq!(get_arguments("").unwrap(), Vec::<String>::new());
+        assert_eq!(
+            get_arguments("-c 000000  -n").unwrap(),
+            vec!["-c", "000000", "-n"]
+        );
+        assert_eq!(
+            get_arguments("--text 'Score {score} is below {threshold}' -u").unwrap(),
+            vec!["--text", "Score {score} is below {threshold}", "-u"]
+        );
+        assert_eq!(
+            get_arguments(r#"--title "Phase: \"{phase}\"" a\ b"#).unwrap(),
+            vec!["--title", "Phase: \"{phase}\"", "a b"]
+        );
+    }
+
+    /// Minimal `org.freedesktop.ScreenSaver` service.
+    struct MockScreenSaver {
+        active: bool,
+    }
+
+    #[zbus::dbus_interface(name = "org.freedesktop.ScreenSaver")]
+    impl MockScreenSaver {
+        fn get_active(&self) -> bool {
+            return self.active;
+        }
+    }
+
+    #[test]
+    fn screen_saver_state_is_queried() {
+        let bus = match TestBus::start() {
+            Some(bus) => bus,
+            None => return,
+        };
+        let connection = bus.connect();
+        assert!(is_screen_saver_active(&connection).is_err());
+
+        let screen_saver = bus
+            .builder()
+            .serve_at(
+                "/org/freedesktop/ScreenSaver",
+                MockScreenSaver { active: false },
+            )
+            .unwrap()
+            .name("org.freedesktop.ScreenSaver")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(!is_screen_saver_active(&connection).unwrap());
+
+        let interface = screen_saver
+            .object_server()
+            .interface::<_, MockScreenSaver>("/org/freedesktop/ScreenSaver")
+            .unwrap();
+        interface.get_mut().active = true;
+        assert!(is_screen_saver_active(&connection).unwrap());
+    }
+
+    #[test]
+    fn unbalanced_quotes_are_an_error() {
+        assert!(get_arguments("--text 'unterminated").is_err());
+        assert!(Backend::new("loginctl", String::new(), "'").is_ok());
+    }
+
+    #[test]
+    fn unknown_backends_are_an_error() {
+        assert!(Backend::new("dbus", String::new(), "").is_ok());
+        assert!(Backend::new("command", String::new(), "").is_ok());
+        assert!(Backend::new("systemd", String::new(), "").is_err());
+        assert!(Backend::new("", String::new(), "").is_err());
+    }
+}
@@ -1,71 +1,437 @@
-use notify_rust::Notification;
-use reqwest::Error;
-use serde::Deserialize;
-use std::process::Command;
-use std::thread;
-use std::time::Duration;
+use reqwest::header;
+use reqwest::{Error, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 use crate::config;
+use crate::data_collector;
+use crate::dbus;
+use crate::health;
+use crate::metrics;
+use crate::systemd;
+
+mod lock;
+mod notification;
+mod phase;
+mod policy;
+mod push;
+mod remote_config;
+mod response;
+mod tray;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Number of seconds a long polling request may take longer than the long
+/// poll timeout before it is aborted.
+const LONG_POLL_TIMEOUT_MARGIN: u64 = 10;
+
+/// Minimum number of seconds between two long polling requests, so servers
+/// answering right away are not flooded with requests.
+const LONG_POLL_INTERVAL: u64 = 1;
+
+/// Maximum number of seconds to wait before retrying a failed status request.
+const MAX_STATUS_RETRY_WAIT: u64 = 600;
+
+/// Number of seconds the status thread may take longer than expected before
+/// it is reported as stuck, long enough for a slow status request.
+const STATUS_OVERDUE_GRACE: u64 = 120;
+
+//==============================================================================
+// Statics
+//==============================================================================
+
+/// The last status received from the remote server.
+static LAST_STATUS: Mutex<Option<Status>> = Mutex::new(None);
+
+/// Sender of the status thread's channel.
+static UPDATE_TX: OnceLock<mpsc::Sender<Update>> = OnceLock::new();
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+/// Message received by the status thread.
+pub enum Update {
+    /// Status pushed by the remote server.
+    Pushed(Status),
+    /// Request to poll the status right away.
+    Poll,
+    /// Request to get the full status right away, even if it did not change
+    /// since the last response.
+    Refresh,
+}
 
 //==============================================================================
 // Structs
 //==============================================================================
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 pub struct Status {
-    pub phase: String,
+    pub phase: phase::Phase,
     pub description: String,
     pub value: f64,
+    #[serde(skip_serializing)]
+    pub config: Option<remote_config::ConfigPatch>,
+}
+
+/// Response of the status API endpoint.
+struct StatusResponse {
+    /// The status, or `None` if it did not change since the last response.
+    status: Option<Status>,
+    /// Entity tag of the status.
+    etag: Option<String>,
+    /// Number of seconds the status is considered fresh for.
+    max_age: Option<u64>,
 }
 
 //==============================================================================
 // Public functions
 //==============================================================================
 
+/// Return the last status received from the remote server.
+pub fn last_status() -> Option<Status> {
+    return LAST_STATUS.lock().unwrap().clone();
+}
+
+/// Poll the status right away instead of waiting for the next poll.
+pub fn poll() -> () {
+    if let Some(update_tx) = UPDATE_TX.get() {
+        match update_tx.send(Update::Poll) {
+            Ok(()) => (),
+            Err(error) => println!("Could not send message: {}", error),
+        }
+    }
+}
+
+/// Get the full status right away, so that it is acted upon even if it did
+/// not change since the last poll.
+pub fn refresh() -> () {
+    if let Some(update_tx) = UPDATE_TX.get() {
+        match update_tx.send(Update::Refresh) {
+            Ok(()) => (),
+            Err(error) => println!("Could not send message: {}", error),
+        }
+    }
+}
+
+/// Check whether the re-authentication command is properly quoted, so that
+/// invalid settings are reported at startup.
+pub fn check_reauth_command(config: &config::Config) -> Result<(), String> {
+    return response::parse_command(config.reauth_command.as_ref().unwrap()).map(|_command| ());
+}
+
+/// Check whether the notification urgencies and timeouts are valid, so that
+/// invalid settings are reported at startup.
+pub fn check_notification(config: &config::Config) -> Result<(), String> {
+    return notification::Notifier::new(
+        config.notification_score_delta.unwrap(),
+        config.notification_urgency.as_ref().unwrap(),
+        config.notification_timeout.as_ref().unwrap(),
+        config.notification_actions.unwrap(),
+    )
+    .map(|_notifier| ());
+}
+
+/// Check whether the lock policy is known, so that invalid settings are
+/// reported at startup.
+pub fn check_lock_policy(config: &config::Config) -> Result<(), String> {
+    return policy::Policy::new(config.lock_policy.as_ref().unwrap()).map(|_policy| ());
+}
+
+/// Check whether the session lock backend is valid, so that invalid settings
+/// are reported at startup.
+pub fn check_lock_backend(config: &config::Config) -> Result<(), String> {
+    return lock::Backend::new(
+        config.lock_backend.as_ref().unwrap(),
+        config.lock_utility.clone().unwrap(),
+        config.lock_arguments.as_ref().unwrap(),
+    )
+    .map(|_backend| ());
+}
+
 pub fn run(config: config::Config, stream_id: String) -> () {
     let status_base_url = config.status_base_url.unwrap();
-    let status_interval = config.status_interval.unwrap();
+    let mut status_interval = config.status_interval.unwrap();
+    let status_push_base_url = config.status_push_base_url.unwrap();
+    let status_long_poll_timeout = config.status_long_poll_timeout.unwrap();
     let lock_utility = config.lock_utility.unwrap();
-    let lock_enabled = config.lock_enabled.unwrap();
-    let lock_threshold = config.lock_threshold.unwrap();
+    let lock_backend = config.lock_backend.unwrap();
+    let lock_arguments = config.lock_arguments.unwrap();
+    let mut lock_enabled = config.lock_enabled.unwrap();
+    let mut lock_threshold = config.lock_threshold.unwrap();
+    let lock_policy = config.lock_policy.unwrap();
+    let lock_policy_window = config.lock_policy_window.unwrap();
+    let lock_rearm_threshold = config.lock_rearm_threshold.unwrap();
+    let lock_cooldown = config.lock_cooldown.unwrap();
+    let warn_threshold = config.warn_threshold.unwrap();
+    let warn_cooldown = config.warn_cooldown.unwrap();
+    let reauth_threshold = config.reauth_threshold.unwrap();
+    let reauth_cooldown = config.reauth_cooldown.unwrap();
+    let reauth_command = config.reauth_command.unwrap();
+    let notification_score_delta = config.notification_score_delta.unwrap();
+    let notification_urgency = config.notification_urgency.unwrap();
+    let notification_timeout = config.notification_timeout.unwrap();
+    let notification_actions = config.notification_actions.unwrap();
+    let tray_enabled = config.tray_enabled.unwrap();
+    let remote_config_enabled = config.remote_config_enabled.unwrap();
+    let remote_config_pinned: Vec<String> = config
+        .remote_config_pinned
+        .unwrap()
+        .split(',')
+        .map(|name| String::from(name.trim()))
+        .filter(|name| !name.is_empty())
+        .collect();
     let user_id = config.user_id.unwrap();
 
     let status_url = format!("{}/{}/{}", status_base_url, user_id, stream_id);
 
+    // The remote configuration may not weaken the local lock settings.
+    let local_lock_enabled = lock_enabled;
+    let local_lock_threshold = lock_threshold;
+
+    // Receive pushed status updates if a push channel is configured.
+    let (update_tx, update_rx) = mpsc::channel();
+    let push_connected = Arc::new(AtomicBool::new(false));
+    if !status_push_base_url.is_empty() {
+        let push_url = format!("{}/{}/{}", status_push_base_url, user_id, stream_id);
+        push::start(push_url, update_tx.clone(), push_connected.clone());
+    }
+    let _ = UPDATE_TX.set(update_tx);
+
+    // The lock backend is validated at startup, see `check_lock_backend`.
+    let lock_backend =
+        match lock::Backend::new(&lock_backend, lock_utility.clone(), &lock_arguments) {
+            Ok(lock_backend) => lock_backend,
+            Err(error) => {
+                println!("Locking the session with the lock utility: {}", error);
+                lock::Backend::Command(lock_utility, Vec::new())
+            }
+        };
+    let mut locker = lock::Locker::new(lock_backend, lock_threshold, lock_cooldown);
+    // The lock policy is validated at startup, see `check_lock_policy`.
+    let lock_policy = match policy::Policy::new(&lock_policy) {
+        Ok(lock_policy) => lock_policy,
+        Err(error) => {
+            println!("Using the immediate lock policy: {}", error);
+            policy::Policy::Immediate
+        }
+    };
+    let mut lock_policy = policy::LockPolicy::new(
+        lock_policy,
+        lock_policy_window,
+        lock_threshold,
+        lock_rearm_threshold,
+    );
+    // The re-authentication command is validated at startup, see
+    // `check_reauth_command`.
+    let reauth_command = match response::parse_command(&reauth_command) {
+        Ok(reauth_command) => reauth_command,
+        Err(error) => {
+            println!("Not re-authenticating the user: {}", error);
+            Vec::new()
+        }
+    };
+    let mut responder = response::Responder::new(
+        warn_threshold,
+        warn_cooldown,
+        reauth_threshold,
+        reauth_cooldown,
+        reauth_command,
+    );
+    // The notification settings are validated at startup, see
+    // `check_notification`.
+    let mut notifier = match notification::Notifier::new(
+        notification_score_delta,
+        &notification_urgency,
+        &notification_timeout,
+        notification_actions,
+    ) {
+        Ok(notifier) => notifier,
+        Err(error) => {
+            println!("Using the default notification settings: {}", error);
+            notification::Notifier::new(notification_score_delta, "", "", notification_actions)
+                .unwrap()
+        }
+    };
+    let tray_icon = tray::TrayIcon::new(tray_enabled, lock_threshold);
+    let mut phase_tracker = phase::PhaseTracker::new();
+    phase_tracker.on_transition(|transition| match &transition.from {
+        Some(from) => println!("Phase changed from {} to {}", from, transition.to),
+        None => println!("Phase is {}", transition.to),
+    });
+    phase_tracker.on_transition(|transition| {
+        let from = match &transition.from {
+            Some(from) => from.to_string(),
+            None => String::new(),
+        };
+        dbus::emit_phase_changed(&from, &transition.to.to_string());
+    });
+    // Scores are only acted upon while verifying, see `Phase::is_enforcing`.
+    let enforcing = Arc::new(AtomicBool::new(false));
+    let hook_enforcing = enforcing.clone();
+    phase_tracker.on_transition(move |transition| {
+        hook_enforcing.store(transition.to.is_enforcing(), Ordering::SeqCst);
+        if transition.from.is_some() && transition.to == phase::Phase::Verify {
+            notification::show_verification_started();
+        }
+    });
+    phase_tracker.on_transition(|transition| {
+        metrics::PHASE.reset();
+        metrics::PHASE
+            .with_label_values(&[&transition.to.to_string()])
+            .set(1);
+    });
+
+    let _liveness = health::start(health::Subsystem::Status);
+    let mut wait = Duration::ZERO;
+    let mut etag = None;
+    let mut failures = 0;
+    health::expect_status_within(get_status_timeout(wait, status_long_poll_timeout));
     loop {
-        // Get status from the remote server.
-        let status = match get_status(&status_url) {
-            Ok(status) => status,
-            Err(error) => panic!("Could not get status: {}", error),
+        // Wait for a pushed status. Get the status from the remote server if
+        // none arrived in time and the push channel is unavailable, or if it
+        // was requested.
+        let update = update_rx.recv_timeout(wait);
+        let refresh = matches!(update, Ok(Update::Refresh));
+        let mut status = match update {
+            Ok(Update::Pushed(status)) => {
+                health::record_status_poll();
+                health::expect_status_within(get_status_timeout(wait, status_long_poll_timeout));
+                status
+            }
+            Err(_error) if push_connected.load(Ordering::SeqCst) => {
+                wait = Duration::from_secs(status_interval);
+                health::expect_status_within(get_status_timeout(wait, status_long_poll_timeout));
+                continue;
+            }
+            Ok(Update::Poll) | Ok(Update::Refresh) | Err(_) => {
+                let timer = metrics::STATUS_POLL_DURATION.start_timer();
+                let started = Instant::now();
+                let request_etag = get_request_etag(&etag, refresh);
+                let response =
+                    match get_status(&status_url, &request_etag, status_long_poll_timeout) {
+                        Ok(response) => response,
+                        Err(error) => {
+                            // Keep the thread alive, the server may be back soon.
+                            timer.stop_and_discard();
+                            println!("Could not get status: {}", error);
+                            metrics::STATUS_POLLS_FAILED.inc();
+                            failures += 1;
+                            wait = get_retry_wait(status_interval, failures);
+                            // The thread makes progress while the server is down.
+                            health::expect_status_within(get_status_timeout(
+                                wait,
+                                status_long_poll_timeout,
+                            ));
+                            continue;
+                        }
+                    };
+                timer.observe_duration();
+                failures = 0;
+                health::record_status_poll();
+                wait = get_poll_wait(
+                    status_interval,
+                    status_long_poll_timeout,
+                    started.elapsed(),
+                    response.max_age,
+                );
+                health::expect_status_within(get_status_timeout(wait, status_long_poll_timeout));
+                if response.etag.is_some() {
+                    etag = response.etag;
+                }
+                match response.status {
+                    Some(status) => status,
+                    // The status did not change since the last response.
+                    None => continue,
+                }
+            }
         };
 
-        // If session locking is enabled and the user's score is lower than a
-        // predefined constant lock the X session by executing the lock utility
-        // program.
-        if lock_enabled &&
-           status.phase == "verify" &&
-           status.value < lock_threshold {
-            Command::new(&lock_utility)
-                .spawn()
-                .expect("Could not lock session.");
+        metrics::LAST_STATUS_TIME.set(chrono::Utc::now().timestamp());
+        metrics::SCORE.set(status.value);
+        systemd::notify_status(&format!("Phase: {}, score: {}", status.phase, status.value));
+
+        *LAST_STATUS.lock().unwrap() = Some(status.clone());
+        dbus::emit_status_changed(&status.phase.to_string(), &status.description, status.value);
+
+        // Apply the configuration patch sent by the remote server.
+        if let Some(patch) = status.config.take() {
+            if remote_config_enabled {
+                let patch = patch.validate(
+                    &remote_config_pinned,
+                    local_lock_enabled,
+                    local_lock_threshold,
+                );
+                println!("Applying remote configuration: {:?}", patch);
+                if let Some(value) = patch.buffer_size_limit {
+                    data_collector::set_buffer_size_limit(value);
+                }
+                if let Some(value) = patch.idle_timeout {
+                    data_collector::set_idle_timeout(value);
+                }
+                if let Some(value) = patch.lock_enabled {
+                    lock_enabled = value;
+                }
+                if let Some(value) = patch.lock_threshold {
+                    lock_threshold = value;
+                    lock_policy.set_lock_threshold(value);
+                    locker.set_lock_threshold(value);
+                }
+                if let Some(value) = patch.status_interval {
+                    status_interval = value;
+                }
+            }
+        }
+
+        // Reap the lock utility if it exited since the last poll.
+        locker.reap();
+
+        // Scores of different phases are not comparable.
+        if phase_tracker.update(&status.phase).is_some() {
+            lock_policy.reset();
+        }
+
+        // If session locking is enabled and the lock policy is triggered by the
+        // user's scores lock the X session, unless the session is already
+        // locked. Scores are only evaluated in the verification phase.
+        let enforcing = enforcing.load(Ordering::SeqCst);
+        let mut locked = false;
+        if lock_enabled && enforcing {
+            if lock_policy.update(status.value) && locker.lock(&status) {
+                lock_policy.disarm();
+                locked = true;
+            }
+        } else {
+            lock_policy.reset();
+        }
+
+        // Lock the X session if the user failed to re-authenticate.
+        let reauthentication_failed = responder.reauthentication_failed();
+        if lock_enabled && enforcing && reauthentication_failed && locker.lock(&status) {
+            locked = true;
+        }
+
+        // Respond to low scores that do not lock the session yet.
+        if !locked {
+            if let Some(action) = responder.respond(&status) {
+                println!("Responded to score {}: {:?}", status.value, action);
+            }
         }
 
         // Notify the user.
-        match Notification::new()
-            .appname("X11 Sentinel Client")
-            .summary("X11 Sentinel Client Status Update")
-            .body(&format!(
-                "Phase: {}, Description: {}, value: {}",
-                status.phase, status.description, status.value
-            ))
-            .show()
-        {
-            Ok(_handle) => (),
-            Err(error) => println!("Could not show notification: {}", error),
-        };
+        notifier.notify(
+            &status,
+            phase_tracker.transitions().last(),
+            locker.last_lock(),
+        );
 
-        // Sleep for a configured amount of time.
-        thread::sleep(Duration::from_secs(status_interval));
+        // Show the status in the tray icon.
+        tray_icon.update(&status, lock_threshold);
     }
 }
 
@@ -73,11 +439,225 @@ pub fn run(config: config::Config, stream_id: String) -> () {
 // Internal functions
 //==============================================================================
 
-/// Send a HTTP GET request to query the status of the client.
+/// Send a HTTP GET request to query the status of the client. The request is
+/// conditional if the entity tag of the last status is known. If the long poll
+/// timeout is not 0, the server is asked to hold the request for that many
+/// seconds until the status changes.
 #[tokio::main]
-async fn get_status(status_url: &String) -> Result<Status, Error> {
-    let response = reqwest::get(status_url).await?;
+async fn get_status(
+    status_url: &String,
+    etag: &Option<String>,
+    long_poll_timeout: u64,
+) -> Result<StatusResponse, Error> {
+    let mut request = reqwest::Client::new().get(status_url);
+    if let Some(etag) = etag {
+        request = request.header(header::IF_NONE_MATCH, etag);
+    }
+    if long_poll_timeout > 0 {
+        request = request
+            .header("Prefer", format!("wait={}", long_poll_timeout))
+            .timeout(Duration::from_secs(
+                long_poll_timeout + LONG_POLL_TIMEOUT_MARGIN,
+            ));
+    }
+    let response = request.send().await?.error_for_status()?;
+
+    let etag = response
+        .headers()
+        .get(header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+    let max_age = response
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_max_age);
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(StatusResponse {
+            status: None,
+            etag,
+            max_age,
+        });
+    }
+
     let status: Status = response.json().await?;
 
-    Ok(status)
+    Ok(StatusResponse {
+        status: Some(status),
+        etag,
+        max_age,
+    })
+}
+
+/// Get the entity tag sent with the status request. A refresh requests the
+/// full status, e.g. to act upon a failed re-authentication right away even
+/// if the status did not change.
+fn get_request_etag(etag: &Option<String>, refresh: bool) -> Option<String> {
+    match refresh {
+        true => return None,
+        false => return etag.clone(),
+    }
+}
+
+/// Parse the `max-age` directive of a `Cache-Control` header.
+fn parse_max_age(cache_control: &str) -> Option<u64> {
+    return cache_control
+        .split(',')
+        .filter_map(|directive| directive.trim().strip_prefix("max-age="))
+        .find_map(|value| value.parse().ok());
+}
+
+/// Get the time to wait before polling the status again. Long polling
+/// requests are sent almost right away, unless the server answered well
+/// before the long poll timeout, as servers ignoring `Prefer: wait` do. A
+/// fresh status is never requested again.
+fn get_poll_wait(
+    status_interval: u64,
+    long_poll_timeout: u64,
+    elapsed: Duration,
+    max_age: Option<u64>,
+) -> Duration {
+    let held = elapsed.as_secs() >= long_poll_timeout / 2;
+    let interval = match long_poll_timeout {
+        0 => status_interval,
+        _ if !held => status_interval,
+        _ => LONG_POLL_INTERVAL,
+    };
+    return Duration::from_secs(interval.max(max_age.unwrap_or(0)));
+}
+
+/// Get the time to wait before retrying a failed status request. The wait
+/// starts at the status interval and doubles with every consecutive failure.
+fn get_retry_wait(status_interval: u64, failures: u32) -> Duration {
+    let factor = 1u64 << failures.saturating_sub(1).min(16);
+    let wait = status_interval.max(1).saturating_mul(factor);
+    return Duration::from_secs(wait.min(MAX_STATUS_RETRY_WAIT));
+}
+
+/// Get the time within which the status thread is expected to request the
+/// next status and get an answer or an error, when waiting for the given time
+/// before requesting it.
+fn get_status_timeout(wait: Duration, long_poll_timeout: u64) -> Duration {
+    let request = long_poll_timeout + LONG_POLL_TIMEOUT_MARGIN;
+    return wait + Duration::from_secs(request + STATUS_OVERDUE_GRACE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn max_age_is_parsed_from_cache_control() {
+        assert_eq!(parse_max_age("max-age=60"), Some(60));
+        assert_eq!(parse_max_age("private, max-age=30"), Some(30));
+        assert_eq!(
+            parse_max_age("no-cache,max-age=5 , must-revalidate"),
+            Some(5)
+        );
+        assert_eq!(parse_max_age("max-age=soon, max-age=7"), Some(7));
+        assert_eq!(parse_max_age("s-maxage=60"), None);
+        assert_eq!(parse_max_age("max-age=-1"), None);
+        assert_eq!(parse_max_age(""), None);
+    }
+
+    #[test]
+    fn poll_wait_uses_status_interval_without_long_polling() {
+        let wait = get_poll_wait(100, 0, Duration::ZERO, None);
+        assert_eq!(wait, Duration::from_secs(100));
+    }
+
+    #[test]
+    fn poll_wait_is_short_after_held_long_poll() {
+        let wait = get_poll_wait(100, 60, Duration::from_secs(60), None);
+        assert_eq!(wait, Duration::from_secs(LONG_POLL_INTERVAL));
+        let wait = get_poll_wait(100, 60, Duration::from_secs(30), None);
+        assert_eq!(wait, Duration::from_secs(LONG_POLL_INTERVAL));
+    }
+
+    #[test]
+    fn poll_wait_uses_status_interval_after_early_long_poll() {
+        // A server ignoring `Prefer: wait` must not be polled every second.
+        let wait = get_poll_wait(100, 60, Duration::from_millis(50), None);
+        assert_eq!(wait, Duration::from_secs(100));
+        let wait = get_poll_wait(100, 60, Duration::from_secs(29), None);
+        assert_eq!(wait, Duration::from_secs(100));
+    }
+
+    #[test]
+    fn poll_wait_respects_max_age() {
+        let wait = get_poll_wait(100, 0, Duration::ZERO, Some(300));
+        assert_eq!(wait, Duration::from_secs(300));
+        let wait = get_poll_wait(100, 60, Duration::from_secs(60), Some(10));
+        assert_eq!(wait, Duration::from_secs(10));
+        let wait = get_poll_wait(100, 0, Duration::ZERO, Some(10));
+        assert_eq!(wait, Duration::from_secs(100));
+    }
+
+    #[test]
+    fn retry_wait_backs_off_exponentially() {
+        assert_eq!(get_retry_wait(10, 1), Duration::from_secs(10));
+        assert_eq!(get_retry_wait(10, 2), Duration::from_secs(20));
+        assert_eq!(get_retry_wait(10, 4), Duration::from_secs(80));
+        assert_eq!(
+            get_retry_wait(10, 100),
+            Duration::from_secs(MAX_STATUS_RETRY_WAIT)
+        );
+        assert_eq!(get_retry_wait(0, 1), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn status_timeout_covers_wait_and_request() {
+        assert_eq!(
+            get_status_timeout(Duration::from_secs(60), 0),
+            Duration::from_secs(60 + LONG_POLL_TIMEOUT_MARGIN + STATUS_OVERDUE_GRACE)
+        );
+        assert!(get_status_timeout(Duration::from_secs(1), 300) > Duration::from_secs(311));
+        // Retries of failed requests extend the deadline as well.
+        let retry = get_retry_wait(10, 100);
+        assert!(get_status_timeout(retry, 0) > Duration::from_secs(MAX_STATUS_RETRY_WAIT));
+    }
+
+    /// Serve the given status on a local port, answering conditional requests
+    /// for the entity tag `"1"` with `304 Not Modified`.
+    fn serve_status(body: &'static str) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/status", server.server_addr());
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let unchanged = request.headers().iter().any(|header| {
+                    header.field.equiv("If-None-Match") && header.value.as_str() == "\"1\""
+                });
+                let etag = tiny_http::Header::from_bytes(&b"ETag"[..], &b"\"1\""[..]).unwrap();
+                let response = match unchanged {
+                    true => tiny_http::Response::from_string("").with_status_code(304),
+                    false => tiny_http::Response::from_string(body).with_status_code(200),
+                };
+                let _result = request.respond(response.with_header(etag));
+            }
+        });
+        return url;
+    }
+
+    #[test]
+    fn refresh_gets_unchanged_status() {
+        let url = serve_status(r#"{"phase":"verify","description":"","value":0.9}"#);
+        let etag = Some(String::from("\"1\""));
+
+        // A poll after the first one learns that the status did not change.
+        let response = get_status(&url, &get_request_etag(&None, false), 0).unwrap();
+        assert_eq!(response.etag, etag);
+        assert!(response.status.is_some());
+        let response = get_status(&url, &get_request_etag(&etag, false), 0).unwrap();
+        assert!(response.status.is_none());
+
+        // A refresh, e.g. after a failed re-authentication, gets the status
+        // again, so that it is acted upon.
+        let response = get_status(&url, &get_request_etag(&etag, true), 0).unwrap();
+        let status = response.status.unwrap();
+        assert_eq!(status.phase, phase::Phase::Verify);
+        assert_eq!(status.value, 0.9);
+    }
 }
@@ -0,0 +1,338 @@
+/**
+ * This module shows the status of the client as a desktop notification.
+ *
+ * A notification is only shown when the phase changes or the score changes
+ * significantly. The same notification is replaced on every update instead of
+ * opening a new one.
+ */
+use notify_rust::{Notification, Timeout, Urgency};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::data_collector;
+use crate::status::lock::LockRecord;
+use crate::status::phase::{Phase, Transition};
+use crate::status::Status;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+const APP_NAME: &str = "X11 Sentinel Client";
+const PAUSE_ACTION: &str = "pause";
+const DETAILS_ACTION: &str = "details";
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Thread waiting for the actions of the shown notification.
+struct Listener {
+    id: u32,
+    running: Arc<AtomicBool>,
+}
+
+pub struct Notifier {
+    score_delta: f64,
+    urgency: HashMap<String, Urgency>,
+    timeout: HashMap<String, Timeout>,
+    actions: bool,
+    id: Option<u32>,
+    last_phase: Option<Phase>,
+    last_value: Option<f64>,
+    body: Arc<Mutex<String>>,
+    listener: Option<Listener>,
+}
+
+impl Notifier {
+    /// Constructor for the `Notifier` object. The urgency and timeout are
+    /// given per phase in the `phase=value,...` format, the `*` phase matches
+    /// every phase that is not listed. Returns an error if a value is invalid.
+    pub fn new(
+        score_delta: f64,
+        urgency: &str,
+        timeout: &str,
+        actions: bool,
+    ) -> Result<Notifier, String> {
+        let urgency = parse_phase_map(urgency, |urgency| {
+            Urgency::try_from(urgency)
+                .map_err(|_error| format!("invalid notification urgency: {}", urgency))
+        })?;
+        let timeout = parse_phase_map(timeout, |timeout| match timeout.parse::<i32>() {
+            Ok(timeout) if timeout >= 0 => Ok(Timeout::from(timeout)),
+            _ => Err(format!("invalid notification timeout: {}", timeout)),
+        })?;
+        return Ok(Notifier {
+            score_delta,
+            urgency,
+            timeout,
+            actions,
+            id: None,
+            last_phase: None,
+            last_value: None,
+            body: Arc::new(Mutex::new(String::new())),
+            listener: None,
+        });
+    }
+
+    /// Notify the user about the status if the phase changed or the score
+    /// changed by at least the configured delta since the last notification.
+    pub fn notify(
+        &mut self,
+        status: &Status,
+        last_transition: Option<&Transition>,
+        last_lock: Option<&LockRecord>,
+    ) -> () {
+        if !self.update(status) {
+            return;
+        }
+
+        // Mention the last phase transition and the last lock in the
+        // notification.
+        let mut body = format_status(status);
+        if let Some(transition) = last_transition {
+            body.push_str(&format!(
+                ", phase since: {}",
+                transition.time.format("%F %T")
+            ));
+        }
+        if let Some(record) = last_lock {
+            body.push_str(&format!(
+                ", last locked at: {}",
+                record.time.format("%F %T")
+            ));
+        }
+
+        let mut notification = Notification::new();
+        notification
+            .appname(APP_NAME)
+            .summary("X11 Sentinel Client Status Update")
+            .body(&body)
+            .urgency(self.get_urgency(&status.phase.to_string()))
+            .timeout(self.get_timeout(&status.phase.to_string()));
+        if let Some(id) = self.id {
+            notification.id(id);
+        }
+        if self.actions {
+            match data_collector::is_paused() {
+                true => notification.action(PAUSE_ACTION, "Resume collection"),
+                false => notification.action(PAUSE_ACTION, "Pause collection"),
+            };
+            notification.action(DETAILS_ACTION, "Details");
+        }
+
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(error) => {
+                println!("Could not show notification: {}", error);
+                return;
+            }
+        };
+        self.id = Some(handle.id());
+
+        if !self.actions {
+            return;
+        }
+
+        // A notification replacing the shown one keeps its id, so a single
+        // thread per id waits for the actions until the notification is
+        // closed or an action is invoked, and uses the latest body.
+        *self.body.lock().unwrap() = body;
+        let listening = match &self.listener {
+            Some(listener) => listener.id == handle.id() && listener.running.load(Ordering::SeqCst),
+            None => false,
+        };
+        if listening {
+            return;
+        }
+        let running = Arc::new(AtomicBool::new(true));
+        self.listener = Some(Listener {
+            id: handle.id(),
+            running: running.clone(),
+        });
+        let body = self.body.clone();
+        thread::spawn(move || {
+            handle.wait_for_action(|action| {
+                let body = body.lock().unwrap().clone();
+                handle_action(action, &body);
+            });
+            running.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// Record the status and return whether the phase changed or the score
+    /// changed by at least the configured delta since the last notification.
+    fn update(&mut self, status: &Status) -> bool {
+        let phase_changed = self.last_phase.as_ref() != Some(&status.phase);
+        let score_changed = match self.last_value {
+            Some(value) => (status.value - value).abs() >= self.score_delta,
+            None => true,
+        };
+        if !phase_changed && !score_changed {
+            return false;
+        }
+        self.last_phase = Some(status.phase.clone());
+        self.last_value = Some(status.value);
+        return true;
+    }
+
+    /// Get the urgency configured for the phase.
+    fn get_urgency(&self, phase: &str) -> Urgency {
+        return get_phase_value(&self.urgency, phase)
+            .copied()
+            .unwrap_or(Urgency::Normal);
+    }
+
+    /// Get the timeout configured for the phase.
+    fn get_timeout(&self, phase: &str) -> Timeout {
+        return get_phase_value(&self.timeout, phase)
+            .copied()
+            .unwrap_or(Timeout::Default);
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Format the status for the notification body.
+pub fn format_status(status: &Status) -> String {
+    return format!(
+        "Phase: {}, Description: {}, value: {}",
+        status.phase, status.description, status.value
+    );
+}
+
+/// Show the details of the status in a separate notification.
+pub fn show_details(body: &str) -> () {
+    match Notification::new()
+        .appname(APP_NAME)
+        .summary("X11 Sentinel Client Status Details")
+        .body(&format!(
+            "{}, collection paused: {}",
+            body,
+            data_collector::is_paused()
+        ))
+        .show()
+    {
+        Ok(_handle) => (),
+        Err(error) => println!("Could not show notification: {}", error),
+    };
+}
+
+/// Tell the user that the verification phase started, so low scores may
+/// lock the session from now on.
+pub fn show_verification_started() -> () {
+    match Notification::new()
+        .appname(APP_NAME)
+        .summary("X11 Sentinel Client Verification")
+        .body("Your profile is trained. From now on, low scores may lock the session.")
+        .show()
+    {
+        Ok(_handle) => (),
+        Err(error) => println!("Could not show notification: {}", error),
+    };
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Handle an action invoked on the notification.
+fn handle_action(action: &str, body: &str) -> () {
+    match action {
+        PAUSE_ACTION => match data_collector::is_paused() {
+            true => data_collector::resume(),
+            false => data_collector::pause(),
+        },
+        DETAILS_ACTION => show_details(body),
+        _ => (),
+    }
+}
+
+/// Parse a `phase=value,...` formatted string, converting the values with the
+/// given function. Empty entries are skipped.
+fn parse_phase_map<T, F: Fn(&str) -> Result<T, String>>(
+    value: &str,
+    parse: F,
+) -> Result<HashMap<String, T>, String> {
+    let mut map = HashMap::new();
+    for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+        let (phase, value) = match pair.split_once('=') {
+            Some((phase, value)) => (phase.trim(), value.trim()),
+            None => return Err(format!("expected phase=value instead of: {}", pair)),
+        };
+        map.insert(String::from(phase), parse(value)?);
+    }
+    return Ok(map);
+}
+
+/// Get the value configured for the phase, falling back to the `*` phase.
+fn get_phase_value<'a, T>(map: &'a HashMap<String, T>, phase: &str) -> Option<&'a T> {
+    return map.get(phase).or_else(|| map.get("*"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(phase: Phase, value: f64) -> Status {
+        return Status {
+            phase,
+            description: String::new(),
+            value,
+            config: None,
+        };
+    }
+
+    fn parse_string(value: &str) -> Result<String, String> {
+        return Ok(String::from(value));
+    }
+
+    #[test]
+    fn phase_or_significant_score_changes_are_notified() {
+        let mut notifier = Notifier::new(0.1, "", "", false).unwrap();
+        assert!(notifier.update(&status(Phase::Train, 0.5)));
+        assert!(!notifier.update(&status(Phase::Train, 0.5)));
+        assert!(!notifier.update(&status(Phase::Train, 0.55)));
+        assert!(notifier.update(&status(Phase::Train, 0.65)));
+        assert!(!notifier.update(&status(Phase::Train, 0.6)));
+        assert!(notifier.update(&status(Phase::Verify, 0.6)));
+        assert!(notifier.update(&status(Phase::Verify, 0.45)));
+    }
+
+    #[test]
+    fn phase_map_is_parsed() {
+        let map = parse_phase_map(" verify = normal ,*=low,", parse_string).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["verify"], "normal");
+        assert_eq!(map["*"], "low");
+        assert!(parse_phase_map("", parse_string).unwrap().is_empty());
+        assert!(parse_phase_map("verify", parse_string).is_err());
+        assert!(parse_phase_map("verify=1,train=x", |value| value
+            .parse::<i32>()
+            .map_err(|error| error.to_string()))
+        .is_err());
+    }
+
+    #[test]
+    fn urgency_falls_back_to_wildcard_and_normal() {
+        let notifier = Notifier::new(0.1, "verify=critical,*=low", "", false).unwrap();
+        assert_eq!(notifier.get_urgency("verify"), Urgency::Critical);
+        assert_eq!(notifier.get_urgency("train"), Urgency::Low);
+        let notifier = Notifier::new(0.1, "verify=critical", "", false).unwrap();
+        assert_eq!(notifier.get_urgency("train"), Urgency::Normal);
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        assert!(Notifier::new(0.1, "verify=urgent", "", false).is_err());
+        assert!(Notifier::new(0.1, "", "verify=soon", false).is_err());
+        assert!(Notifier::new(0.1, "", "verify=-1", false).is_err());
+        let notifier = Notifier::new(0.1, "", "verify=0,*=5000", false).unwrap();
+        assert_eq!(notifier.get_timeout("verify"), Timeout::Never);
+        assert_eq!(notifier.get_timeout("train"), Timeout::Milliseconds(5000));
+    }
+}
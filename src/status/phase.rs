@@ -0,0 +1,203 @@
+/**
+ * This module implements the phases reported by the status API endpoint and
+ * tracks the transitions between them.
+ */
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// Number of transitions kept in the history.
+const TRANSITION_HISTORY_SIZE: usize = 100;
+
+//==============================================================================
+// Types
+//==============================================================================
+
+/// Hook called on phase transitions.
+type Hook = Box<dyn Fn(&Transition) + Send>;
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+/// Phase of the user's profile on the remote server. Phases unknown to the
+/// client are kept as `Unknown` instead of failing the deserialization.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum Phase {
+    Enroll,
+    Train,
+    Verify,
+    Unknown(String),
+}
+
+impl Phase {
+    /// Whether the scores are acted upon in the phase. The profile is still
+    /// being built while enrolling and training, so low scores neither lock
+    /// the session nor warn the user.
+    pub fn is_enforcing(&self) -> bool {
+        return *self == Phase::Verify;
+    }
+}
+
+impl From<String> for Phase {
+    fn from(value: String) -> Phase {
+        match value.as_str() {
+            "enroll" => Phase::Enroll,
+            "train" => Phase::Train,
+            "verify" => Phase::Verify,
+            _ => Phase::Unknown(value),
+        }
+    }
+}
+
+impl From<Phase> for String {
+    fn from(phase: Phase) -> String {
+        return phase.to_string();
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Phase::Enroll => write!(f, "enroll"),
+            Phase::Train => write!(f, "train"),
+            Phase::Verify => write!(f, "verify"),
+            Phase::Unknown(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Transition between two phases.
+#[derive(Clone, Debug)]
+pub struct Transition {
+    pub from: Option<Phase>,
+    pub to: Phase,
+    pub time: DateTime<Local>,
+}
+
+/// State machine tracking the phase of the client.
+pub struct PhaseTracker {
+    current: Option<Phase>,
+    transitions: Vec<Transition>,
+    hooks: Vec<Hook>,
+}
+
+impl PhaseTracker {
+    /// Constructor for the `PhaseTracker` object.
+    pub fn new() -> PhaseTracker {
+        PhaseTracker {
+            current: None,
+            transitions: Vec::new(),
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Register a hook that is called on every phase transition.
+    pub fn on_transition<F>(&mut self, hook: F) -> ()
+    where
+        F: Fn(&Transition) + Send + 'static,
+    {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Record the phase reported by the remote server. The transition is
+    /// returned and the hooks are called if the phase changed.
+    pub fn update(&mut self, phase: &Phase) -> Option<Transition> {
+        if self.current.as_ref() == Some(phase) {
+            return None;
+        }
+
+        let transition = Transition {
+            from: self.current.replace(phase.clone()),
+            to: phase.clone(),
+            time: Local::now(),
+        };
+
+        if self.transitions.len() == TRANSITION_HISTORY_SIZE {
+            self.transitions.remove(0);
+        }
+        self.transitions.push(transition.clone());
+
+        for hook in &self.hooks {
+            hook(&transition);
+        }
+        return Some(transition);
+    }
+
+    /// Return the recorded transitions, the oldest first.
+    pub fn transitions(&self) -> &[Transition] {
+        return &self.transitions;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn phases_round_trip_through_json() {
+        let cases = [
+            ("\"enroll\"", Phase::Enroll),
+            ("\"train\"", Phase::Train),
+            ("\"verify\"", Phase::Verify),
+            ("\"retrain\"", Phase::Unknown(String::from("retrain"))),
+            ("\"\"", Phase::Unknown(String::new())),
+        ];
+        for (json, phase) in cases {
+            assert_eq!(serde_json::from_str::<Phase>(json).unwrap(), phase);
+            assert_eq!(serde_json::to_string(&phase).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn only_verify_is_enforcing() {
+        assert!(!Phase::Enroll.is_enforcing());
+        assert!(!Phase::Train.is_enforcing());
+        assert!(Phase::Verify.is_enforcing());
+        assert!(!Phase::Unknown(String::from("verify2")).is_enforcing());
+    }
+
+    #[test]
+    fn transitions_are_reported_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let hook_calls = calls.clone();
+        let mut tracker = PhaseTracker::new();
+        tracker.on_transition(move |_transition| {
+            hook_calls.fetch_add(1, Ordering::SeqCst);
+        });
+
+        let transition = tracker.update(&Phase::Enroll).unwrap();
+        assert_eq!(transition.from, None);
+        assert_eq!(transition.to, Phase::Enroll);
+        assert!(tracker.update(&Phase::Enroll).is_none());
+        let transition = tracker.update(&Phase::Train).unwrap();
+        assert_eq!(transition.from, Some(Phase::Enroll));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut tracker = PhaseTracker::new();
+        for index in 0..(TRANSITION_HISTORY_SIZE + 50) {
+            tracker.update(&Phase::Unknown(index.to_string()));
+        }
+        let transitions = tracker.transitions();
+        assert_eq!(transitions.len(), TRANSITION_HISTORY_SIZE);
+        assert_eq!(transitions[0].to, Phase::Unknown(String::from("50")));
+        assert_eq!(
+            transitions.last().unwrap().to,
+            Phase::Unknown((TRANSITION_HISTORY_SIZE + 49).to_string())
+        );
+    }
+}
@@ -0,0 +1,251 @@
+/**
+ * This module decides whether a sequence of scores should lock the session.
+ *
+ * Scores are collected in a window and evaluated by the configured policy.
+ * Once the session was locked, the policy is disarmed until a score reaches
+ * the re-arm threshold, so a session is not locked over and over again by the
+ * same streak of low scores.
+ */
+use std::collections::VecDeque;
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Policy {
+    /// Trigger on a single score below the lock threshold.
+    Immediate,
+    /// Trigger when the last `window` scores are all below the lock threshold.
+    Consecutive,
+    /// Trigger when the average of the last `window` scores is below the lock
+    /// threshold.
+    Average,
+}
+
+impl Policy {
+    /// Constructor for the `Policy` object. Returns an error for unknown
+    /// policy names.
+    pub fn new(name: &str) -> Result<Policy, String> {
+        match name {
+            "immediate" => return Ok(Policy::Immediate),
+            "consecutive" => return Ok(Policy::Consecutive),
+            "average" => return Ok(Policy::Average),
+            _ => return Err(format!("unknown lock policy: {}", name)),
+        }
+    }
+}
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+pub struct LockPolicy {
+    policy: Policy,
+    window: usize,
+    lock_threshold: f64,
+    rearm_threshold: f64,
+    configured_rearm_threshold: f64,
+    scores: VecDeque<f64>,
+    armed: bool,
+}
+
+impl LockPolicy {
+    /// Constructor for the `LockPolicy` object. A re-arm threshold below the
+    /// lock threshold is raised to the lock threshold.
+    pub fn new(
+        policy: Policy,
+        window: usize,
+        lock_threshold: f64,
+        rearm_threshold: f64,
+    ) -> LockPolicy {
+        let window = match policy {
+            Policy::Immediate => 1,
+            _ => window.max(1),
+        };
+        LockPolicy {
+            policy,
+            window,
+            lock_threshold,
+            rearm_threshold: rearm_threshold.max(lock_threshold),
+            configured_rearm_threshold: rearm_threshold,
+            scores: VecDeque::with_capacity(window),
+            armed: true,
+        }
+    }
+
+    /// Record a new score and return whether the session should be locked.
+    /// The policy stays armed until `disarm` is called.
+    pub fn update(&mut self, value: f64) -> bool {
+        // Re-arm once the score recovered.
+        if !self.armed {
+            if value >= self.rearm_threshold {
+                self.armed = true;
+                self.scores.clear();
+            }
+            return false;
+        }
+
+        if self.scores.len() == self.window {
+            self.scores.pop_front();
+        }
+        self.scores.push_back(value);
+
+        return self.is_triggered();
+    }
+
+    /// Disarm the policy after the session was locked, until a score reaches
+    /// the re-arm threshold.
+    pub fn disarm(&mut self) -> () {
+        self.armed = false;
+        self.scores.clear();
+    }
+
+    /// Change the lock threshold. The re-arm threshold is raised to the new
+    /// lock threshold if needed.
+    pub fn set_lock_threshold(&mut self, lock_threshold: f64) -> () {
+        self.lock_threshold = lock_threshold;
+        self.rearm_threshold = self.configured_rearm_threshold.max(lock_threshold);
+    }
+
+    /// Forget the recorded scores, e.g. when the scores are not comparable
+    /// with the previous ones anymore.
+    pub fn reset(&mut self) -> () {
+        self.scores.clear();
+    }
+
+    /// Whether the recorded scores satisfy the policy.
+    fn is_triggered(&self) -> bool {
+        if self.scores.len() < self.window {
+            return false;
+        }
+        match self.policy {
+            Policy::Immediate | Policy::Consecutive => {
+                return self.scores.iter().all(|score| *score < self.lock_threshold);
+            }
+            Policy::Average => {
+                let sum: f64 = self.scores.iter().sum();
+                return sum / (self.scores.len() as f64) < self.lock_threshold;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOCK_THRESHOLD: f64 = 0.5;
+    const REARM_THRESHOLD: f64 = 0.7;
+
+    /// Feed the scores to a policy that is disarmed whenever it triggers and
+    /// return the scores' triggers.
+    fn run(policy: Policy, window: usize, scores: &[f64]) -> Vec<bool> {
+        let mut lock_policy = LockPolicy::new(policy, window, LOCK_THRESHOLD, REARM_THRESHOLD);
+        return scores
+            .iter()
+            .map(|score| {
+                let triggered = lock_policy.update(*score);
+                if triggered {
+                    lock_policy.disarm();
+                }
+                triggered
+            })
+            .collect();
+    }
+
+    #[test]
+    fn policies_trigger_on_low_scores() {
+        let cases: &[(Policy, usize, &[f64], &[bool])] = &[
+            (Policy::Immediate, 3, &[0.9, 0.4], &[false, true]),
+            (Policy::Immediate, 3, &[0.5, 0.6], &[false, false]),
+            (
+                Policy::Consecutive,
+                3,
+                &[0.4, 0.4, 0.9, 0.4, 0.4, 0.4],
+                &[false, false, false, false, false, true],
+            ),
+            (Policy::Consecutive, 1, &[0.9, 0.4], &[false, true]),
+            (
+                Policy::Average,
+                3,
+                &[0.9, 0.3, 0.3, 0.1],
+                &[false, false, false, true],
+            ),
+            (
+                Policy::Average,
+                2,
+                &[0.6, 0.5, 0.45, 0.5],
+                &[false, false, true, false],
+            ),
+        ];
+        for (policy, window, scores, expected) in cases {
+            assert_eq!(
+                run(*policy, *window, scores),
+                *expected,
+                "{:?} with window {} and scores {:?}",
+                policy,
+                window,
+                scores
+            );
+        }
+    }
+
+    #[test]
+    fn policy_rearms_at_rearm_threshold() {
+        let cases: &[(&[f64], &[bool])] = &[
+            // Scores between the thresholds do not re-arm the policy.
+            (&[0.4, 0.6, 0.4], &[true, false, false]),
+            (&[0.4, 0.7, 0.4], &[true, false, true]),
+            (
+                &[0.4, 0.3, 0.8, 0.6, 0.2],
+                &[true, false, false, false, true],
+            ),
+        ];
+        for (scores, expected) in cases {
+            assert_eq!(
+                run(Policy::Immediate, 1, scores),
+                *expected,
+                "scores {:?}",
+                scores
+            );
+        }
+    }
+
+    #[test]
+    fn rearm_threshold_is_at_least_lock_threshold() {
+        let mut lock_policy = LockPolicy::new(Policy::Immediate, 1, LOCK_THRESHOLD, 0.2);
+        assert!(lock_policy.update(0.4));
+        lock_policy.disarm();
+        assert!(!lock_policy.update(0.45));
+        assert!(!lock_policy.update(0.5));
+        assert!(lock_policy.update(0.4));
+    }
+
+    #[test]
+    fn policy_stays_armed_until_disarmed() {
+        let mut lock_policy =
+            LockPolicy::new(Policy::Immediate, 1, LOCK_THRESHOLD, REARM_THRESHOLD);
+        assert!(lock_policy.update(0.4));
+        // The session could not be locked, so the next low score triggers.
+        assert!(lock_policy.update(0.4));
+    }
+
+    #[test]
+    fn reset_forgets_scores() {
+        let mut lock_policy =
+            LockPolicy::new(Policy::Consecutive, 2, LOCK_THRESHOLD, REARM_THRESHOLD);
+        assert!(!lock_policy.update(0.4));
+        lock_policy.reset();
+        assert!(!lock_policy.update(0.4));
+        assert!(lock_policy.update(0.4));
+    }
+
+    #[test]
+    fn unknown_policies_are_an_error() {
+        assert_eq!(Policy::new("immediate"), Ok(Policy::Immediate));
+        assert_eq!(Policy::new("consecutive"), Ok(Policy::Consecutive));
+        assert_eq!(Policy::new("average"), Ok(Policy::Average));
+        assert!(Policy::new("median").is_err());
+    }
+}
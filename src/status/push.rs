@@ -0,0 +1,238 @@
+/**
+ * This module receives status updates pushed by the remote server as
+ * Server-Sent Events. Every event carries a status in its `data` field.
+ *
+ * The connection is re-established with an exponential backoff when it fails
+ * or stays silent for too long.
+ */
+use reqwest::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::status::{Status, Update};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+/// The connection is considered dead if nothing (not even a keep-alive
+/// comment) is received for this number of seconds.
+const READ_TIMEOUT: u64 = 120;
+
+/// Initial and maximal delay in seconds before reconnecting.
+const MIN_RECONNECT_DELAY: u64 = 1;
+const MAX_RECONNECT_DELAY: u64 = 60;
+
+/// Maximal size in bytes of an incomplete event. The connection is
+/// re-established if the server sends more without ending the event.
+const MAX_EVENT_SIZE: usize = 64 * 1024;
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Splits the received bytes into events.
+struct EventReader {
+    buffer: Vec<u8>,
+}
+
+impl EventReader {
+    /// Constructor for the `EventReader` object.
+    fn new() -> EventReader {
+        EventReader { buffer: Vec::new() }
+    }
+
+    /// Append the received chunk and return the events it completes. Returns
+    /// an error if the incomplete event grows larger than `MAX_EVENT_SIZE`.
+    fn read(&mut self, chunk: &[u8]) -> Result<Vec<String>, String> {
+        self.buffer
+            .extend(chunk.iter().filter(|byte| **byte != b'\r'));
+
+        // Events are separated by an empty line.
+        let mut events = Vec::new();
+        while let Some(index) = self.buffer.windows(2).position(|bytes| bytes == b"\n\n") {
+            let event: Vec<u8> = self.buffer.drain(..index + 2).collect();
+            events.push(String::from(String::from_utf8_lossy(&event)));
+        }
+        if self.buffer.len() > MAX_EVENT_SIZE {
+            return Err(format!(
+                "event exceeds {} bytes without ending",
+                MAX_EVENT_SIZE
+            ));
+        }
+        return Ok(events);
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Start receiving pushed statuses in a background thread. The statuses are
+/// sent to `tx`, `connected` tells whether the push channel is available.
+pub fn start(push_url: String, tx: mpsc::Sender<Update>, connected: Arc<AtomicBool>) -> () {
+    thread::spawn(move || {
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            match listen(&push_url, &tx, &connected, &mut delay) {
+                Ok(()) => println!("Status push channel closed"),
+                Err(error) => println!("Status push channel failed: {}", error),
+            }
+            connected.store(false, Ordering::SeqCst);
+
+            thread::sleep(Duration::from_secs(delay));
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    });
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Connect to the push channel and forward the received statuses until the
+/// connection is closed.
+#[tokio::main]
+async fn listen(
+    push_url: &str,
+    tx: &mpsc::Sender<Update>,
+    connected: &AtomicBool,
+    delay: &mut u64,
+) -> Result<(), Error> {
+    let mut response = reqwest::Client::new()
+        .get(push_url)
+        .header("Accept", "text/event-stream")
+        .send()
+        .await?
+        .error_for_status()?;
+
+    connected.store(true, Ordering::SeqCst);
+    *delay = MIN_RECONNECT_DELAY;
+
+    let mut reader = EventReader::new();
+    loop {
+        let read = tokio::time::timeout(Duration::from_secs(READ_TIMEOUT), response.chunk());
+        let chunk = match read.await {
+            Ok(chunk) => chunk?,
+            Err(_elapsed) => {
+                println!("Status push channel timed out");
+                return Ok(());
+            }
+        };
+        let chunk = match chunk {
+            Some(chunk) => chunk,
+            None => return Ok(()),
+        };
+        let events = match reader.read(&chunk) {
+            Ok(events) => events,
+            Err(error) => {
+                println!("Status push channel sent an invalid event: {}", error);
+                return Ok(());
+            }
+        };
+        for event in events {
+            if let Some(status) = parse_event(&event) {
+                if tx.send(Update::Pushed(status)).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Parse the status carried by the `data` field of an event. Comments and
+/// other fields are ignored.
+fn parse_event(event: &str) -> Option<Status> {
+    let data: Vec<&str> = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return None;
+    }
+    match serde_json::from_str(&data.join("\n")) {
+        Ok(status) => return Some(status),
+        Err(error) => {
+            println!("Could not parse pushed status: {}", error);
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::status::phase::Phase;
+
+    const STATUS: &str = r#"{"phase":"verify","description":"ok","value":0.5}"#;
+
+    #[test]
+    fn status_is_parsed_from_data() {
+        let status = parse_event(&format!("data: {}\n\n", STATUS)).unwrap();
+        assert_eq!(status.phase, Phase::Verify);
+        assert_eq!(status.description, "ok");
+        assert_eq!(status.value, 0.5);
+        assert!(parse_event("data: {\n\n").is_none());
+        assert!(parse_event(": keep-alive\n\n").is_none());
+    }
+
+    #[test]
+    fn data_lines_are_joined() {
+        let event =
+            "data: {\"phase\":\"verify\",\ndata:\"description\":\"ok\",\ndata: \"value\":0.5}\n\n";
+        let status = parse_event(event).unwrap();
+        assert_eq!(status.description, "ok");
+    }
+
+    #[test]
+    fn comments_and_event_names_are_ignored() {
+        let event = format!(": comment\nevent: status\nid: 1\ndata: {}\n\n", STATUS);
+        assert_eq!(parse_event(&event).unwrap().value, 0.5);
+    }
+
+    #[test]
+    fn events_are_split_across_chunks() {
+        let stream = format!(": keep-alive\n\ndata: {}\n\ndata: {}\n\n", STATUS, STATUS);
+        for size in [1, 3, 7, stream.len()] {
+            let mut reader = EventReader::new();
+            let mut events = Vec::new();
+            for chunk in stream.as_bytes().chunks(size) {
+                events.extend(reader.read(chunk).unwrap());
+            }
+            assert_eq!(events.len(), 3);
+            assert!(parse_event(&events[0]).is_none());
+            assert_eq!(parse_event(&events[1]).unwrap().value, 0.5);
+            assert_eq!(parse_event(&events[2]).unwrap().value, 0.5);
+        }
+    }
+
+    #[test]
+    fn carriage_returns_are_stripped() {
+        let mut reader = EventReader::new();
+        let events = reader
+            .read(format!("data: {}\r\n\r\ndata: {}\r\n", STATUS, STATUS).as_bytes())
+            .unwrap();
+        assert_eq!(events, vec![format!("data: {}\n\n", STATUS)]);
+        let events = reader.read(b"\r\n").unwrap();
+        assert_eq!(events, vec![format!("data: {}\n\n", STATUS)]);
+    }
+
+    #[test]
+    fn unterminated_events_are_limited() {
+        let mut reader = EventReader::new();
+        let chunk = vec![b'x'; MAX_EVENT_SIZE];
+        assert!(reader.read(&chunk).unwrap().is_empty());
+        assert!(reader.read(b"x").is_err());
+
+        // Complete events do not count against the limit.
+        let mut reader = EventReader::new();
+        let mut chunk = vec![b'x'; MAX_EVENT_SIZE];
+        chunk.extend(b"\n\nx");
+        assert_eq!(reader.read(&chunk).unwrap().len(), 1);
+    }
+}
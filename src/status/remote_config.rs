@@ -0,0 +1,149 @@
+/**
+ * This module validates the configuration patches sent by the remote server
+ * in the status response.
+ *
+ * Values outside of the allowed bounds, settings pinned by the local
+ * configuration and values weakening the locally configured session locking
+ * are dropped from the patch.
+ */
+use serde::Deserialize;
+use std::fmt::Display;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+const BUFFER_SIZE_LIMIT_BOUNDS: (usize, usize) = (1, 10000);
+const IDLE_TIMEOUT_BOUNDS: (u64, u64) = (1000, 600000);
+const LOCK_THRESHOLD_BOUNDS: (f64, f64) = (0.0, 1.0);
+const STATUS_INTERVAL_BOUNDS: (u64, u64) = (1, 3600);
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigPatch {
+    pub buffer_size_limit: Option<usize>,
+    pub idle_timeout: Option<u64>,
+    pub lock_enabled: Option<bool>,
+    pub lock_threshold: Option<f64>,
+    pub status_interval: Option<u64>,
+}
+
+impl ConfigPatch {
+    /// Return the patch without the values that are out of bounds or pinned
+    /// by the local configuration. Pinned settings are given by their
+    /// configuration field name, e.g. `lock_enabled`. The remote server may
+    /// tighten session locking, but may neither disable locking nor lower the
+    /// lock threshold below the local configuration.
+    pub fn validate(
+        self,
+        pinned: &[String],
+        lock_enabled: bool,
+        lock_threshold: f64,
+    ) -> ConfigPatch {
+        ConfigPatch {
+            buffer_size_limit: self.buffer_size_limit.filter(|value| {
+                is_accepted("buffer_size_limit", value, pinned, BUFFER_SIZE_LIMIT_BOUNDS)
+            }),
+            idle_timeout: self
+                .idle_timeout
+                .filter(|value| is_accepted("idle_timeout", value, pinned, IDLE_TIMEOUT_BOUNDS)),
+            lock_enabled: self
+                .lock_enabled
+                .filter(|value| is_accepted("lock_enabled", value, pinned, (lock_enabled, true))),
+            lock_threshold: self.lock_threshold.filter(|value| {
+                let bounds = (
+                    lock_threshold.max(LOCK_THRESHOLD_BOUNDS.0),
+                    LOCK_THRESHOLD_BOUNDS.1,
+                );
+                is_accepted("lock_threshold", value, pinned, bounds)
+            }),
+            status_interval: self.status_interval.filter(|value| {
+                is_accepted("status_interval", value, pinned, STATUS_INTERVAL_BOUNDS)
+            }),
+        }
+    }
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Whether the setting is not pinned and its value is within the inclusive
+/// bounds.
+fn is_accepted<T: PartialOrd + Display>(
+    name: &str,
+    value: &T,
+    pinned: &[String],
+    bounds: (T, T),
+) -> bool {
+    if pinned.iter().any(|pinned| pinned == name) {
+        println!("Ignoring remote {} {}, the setting is pinned", name, value);
+        return false;
+    }
+    if *value < bounds.0 || *value > bounds.1 {
+        println!(
+            "Ignoring remote {} {}, allowed range is {} - {}",
+            name, value, bounds.0, bounds.1
+        );
+        return false;
+    }
+    return true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_lock_patch(lock_enabled: bool, lock_threshold: f64) -> ConfigPatch {
+        ConfigPatch {
+            lock_enabled: Some(lock_enabled),
+            lock_threshold: Some(lock_threshold),
+            ..ConfigPatch::default()
+        }
+    }
+
+    #[test]
+    fn locking_may_be_tightened() {
+        let patch = get_lock_patch(true, 0.5).validate(&[], false, 0.3);
+        assert_eq!(patch.lock_enabled, Some(true));
+        assert_eq!(patch.lock_threshold, Some(0.5));
+    }
+
+    #[test]
+    fn locking_may_not_be_weakened() {
+        let patch = get_lock_patch(false, 0.2).validate(&[], true, 0.3);
+        assert_eq!(patch.lock_enabled, None);
+        assert_eq!(patch.lock_threshold, None);
+    }
+
+    #[test]
+    fn local_lock_settings_may_be_restored() {
+        let patch = get_lock_patch(false, 0.3).validate(&[], false, 0.3);
+        assert_eq!(patch.lock_enabled, Some(false));
+        assert_eq!(patch.lock_threshold, Some(0.3));
+    }
+
+    #[test]
+    fn pinned_and_out_of_bounds_values_are_dropped() {
+        let patch = ConfigPatch {
+            buffer_size_limit: Some(0),
+            idle_timeout: Some(5000),
+            status_interval: Some(60),
+            ..get_lock_patch(true, 0.9)
+        };
+        let pinned = vec![
+            String::from("status_interval"),
+            String::from("lock_threshold"),
+        ];
+        let patch = patch.validate(&pinned, true, 0.3);
+        assert_eq!(patch.buffer_size_limit, None);
+        assert_eq!(patch.idle_timeout, Some(5000));
+        assert_eq!(patch.lock_enabled, Some(true));
+        assert_eq!(patch.lock_threshold, None);
+        assert_eq!(patch.status_interval, None);
+    }
+}
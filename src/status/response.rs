@@ -0,0 +1,310 @@
+/**
+ * This module implements the responses to low scores that precede locking the
+ * session: a warning notification and a re-authentication command. Every
+ * response has its own threshold and cooldown period.
+ */
+use notify_rust::{Notification, Urgency};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::status;
+use crate::status::Status;
+
+//==============================================================================
+// Enums
+//==============================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    /// Show a warning notification.
+    Warn,
+    /// Execute the re-authentication command.
+    Reauthenticate,
+}
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+struct Tier {
+    action: Action,
+    threshold: f64,
+    cooldown: Duration,
+    last_run: Option<Instant>,
+}
+
+impl Tier {
+    /// Whether the tier responds to the given score.
+    fn is_triggered(&self, value: f64) -> bool {
+        if value >= self.threshold {
+            return false;
+        }
+        match self.last_run {
+            Some(last_run) => return last_run.elapsed() >= self.cooldown,
+            None => return true,
+        }
+    }
+}
+
+pub struct Responder {
+    tiers: Vec<Tier>,
+    reauth_command: Vec<String>,
+    reauth_running: Arc<AtomicBool>,
+    reauth_failed: Arc<AtomicBool>,
+}
+
+impl Responder {
+    /// Constructor for the `Responder` object. Cooldowns are given in seconds.
+    /// The re-authentication tier is disabled if no command is given, see
+    /// `parse_command`.
+    pub fn new(
+        warn_threshold: f64,
+        warn_cooldown: u64,
+        reauth_threshold: f64,
+        reauth_cooldown: u64,
+        reauth_command: Vec<String>,
+    ) -> Responder {
+        let mut tiers = vec![Tier {
+            action: Action::Warn,
+            threshold: warn_threshold,
+            cooldown: Duration::from_secs(warn_cooldown),
+            last_run: None,
+        }];
+        if !reauth_command.is_empty() {
+            tiers.push(Tier {
+                action: Action::Reauthenticate,
+                threshold: reauth_threshold,
+                cooldown: Duration::from_secs(reauth_cooldown),
+                last_run: None,
+            });
+        }
+        Responder {
+            tiers,
+            reauth_command,
+            reauth_running: Arc::new(AtomicBool::new(false)),
+            reauth_failed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Respond to the given status, see `select`.
+    pub fn respond(&mut self, status: &Status) -> Option<Action> {
+        let action = self.select(status)?;
+        match action {
+            Action::Warn => warn(status),
+            Action::Reauthenticate => {
+                self.reauthenticate();
+            }
+        }
+        return Some(action);
+    }
+
+    /// Select the most severe action whose threshold is above the score and
+    /// whose cooldown elapsed, and start its cooldown. Scores are only acted
+    /// upon in the phases enforcing them.
+    fn select(&mut self, status: &Status) -> Option<Action> {
+        if !status.phase.is_enforcing() {
+            return None;
+        }
+        let tier = self
+            .tiers
+            .iter_mut()
+            .filter(|tier| tier.is_triggered(status.value))
+            .min_by(|a, b| a.threshold.total_cmp(&b.threshold))?;
+        tier.last_run = Some(Instant::now());
+        return Some(tier.action);
+    }
+
+    /// Return whether the re-authentication command failed since the last
+    /// call.
+    pub fn reauthentication_failed(&mut self) -> bool {
+        return self.reauth_failed.swap(false, Ordering::SeqCst);
+    }
+
+    /// Execute the re-authentication command, unless it is already running.
+    /// A failure is recorded once the command exits with a non-zero status
+    /// and the full status is requested right away, so it is acted upon
+    /// without waiting for the next status change. Returns whether the
+    /// command was started.
+    fn reauthenticate(&mut self) -> bool {
+        if self.reauth_running.load(Ordering::SeqCst) {
+            return false;
+        }
+        let (program, arguments) = match self.reauth_command.split_first() {
+            Some(command) => command,
+            None => return false,
+        };
+        let mut child = match Command::new(program).args(arguments).spawn() {
+            Ok(child) => child,
+            Err(error) => {
+                println!("Could not execute re-authentication command: {}", error);
+                return false;
+            }
+        };
+
+        self.reauth_running.store(true, Ordering::SeqCst);
+        let running = self.reauth_running.clone();
+        let failed = self.reauth_failed.clone();
+        thread::spawn(move || {
+            let success = match child.wait() {
+                Ok(exit_status) => exit_status.success(),
+                Err(error) => {
+                    println!("Could not wait for re-authentication command: {}", error);
+                    false
+                }
+            };
+            failed.store(!success, Ordering::SeqCst);
+            running.store(false, Ordering::SeqCst);
+            if !success {
+                status::refresh();
+            }
+        });
+        return true;
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Split the re-authentication command like a shell does. An error is
+/// returned if it is not properly quoted.
+pub fn parse_command(command: &str) -> Result<Vec<String>, String> {
+    return shell_words::split(command)
+        .map_err(|error| format!("invalid re-authentication command: {}", error));
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Warn the user that the session may get locked.
+fn warn(status: &Status) -> () {
+    match Notification::new()
+        .appname("X11 Sentinel Client")
+        .summary("X11 Sentinel Client Warning")
+        .body(&format!(
+            "Your score is low ({}), the session may get locked.",
+            status.value
+        ))
+        .urgency(Urgency::Critical)
+        .show()
+    {
+        Ok(_handle) => (),
+        Err(error) => println!("Could not show notification: {}", error),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::status::phase::Phase;
+
+    fn status(phase: Phase, value: f64) -> Status {
+        return Status {
+            phase,
+            description: String::new(),
+            value,
+            config: None,
+        };
+    }
+
+    fn command(command: &str) -> Vec<String> {
+        return parse_command(command).unwrap();
+    }
+
+    /// Wait until the re-authentication command exited.
+    fn wait_for_exit(responder: &Responder) -> () {
+        for _attempt in 0..100 {
+            if !responder.reauth_running.load(Ordering::SeqCst) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("The re-authentication command did not exit");
+    }
+
+    #[test]
+    fn most_severe_tier_is_selected_by_score() {
+        let mut responder = Responder::new(0.5, 0, 0.3, 0, command("true"));
+        assert_eq!(responder.select(&status(Phase::Verify, 0.6)), None);
+        assert_eq!(responder.select(&status(Phase::Verify, 0.5)), None);
+        assert_eq!(
+            responder.select(&status(Phase::Verify, 0.4)),
+            Some(Action::Warn)
+        );
+        assert_eq!(
+            responder.select(&status(Phase::Verify, 0.2)),
+            Some(Action::Reauthenticate)
+        );
+    }
+
+    #[test]
+    fn scores_are_only_acted_upon_while_verifying() {
+        let mut responder = Responder::new(0.5, 0, 0.3, 0, command("true"));
+        for phase in [
+            Phase::Enroll,
+            Phase::Train,
+            Phase::Unknown(String::from("retrain")),
+        ] {
+            assert_eq!(responder.select(&status(phase, 0.1)), None);
+        }
+        assert_eq!(
+            responder.select(&status(Phase::Verify, 0.1)),
+            Some(Action::Reauthenticate)
+        );
+    }
+
+    #[test]
+    fn tiers_have_their_own_cooldowns() {
+        let mut responder = Responder::new(0.5, 3600, 0.3, 3600, command("true"));
+        let low = status(Phase::Verify, 0.1);
+        assert_eq!(responder.select(&low), Some(Action::Reauthenticate));
+        // The warning is shown while re-authentication cools down.
+        assert_eq!(responder.select(&low), Some(Action::Warn));
+        assert_eq!(responder.select(&low), None);
+    }
+
+    #[test]
+    fn reauthentication_is_disabled_without_command() {
+        let mut responder = Responder::new(0.5, 0, 0.3, 0, Vec::new());
+        assert_eq!(
+            responder.select(&status(Phase::Verify, 0.1)),
+            Some(Action::Warn)
+        );
+        assert!(!responder.reauthenticate());
+    }
+
+    #[test]
+    fn running_reauthentication_is_not_started_again() {
+        let mut responder = Responder::new(0.5, 0, 0.3, 0, command("sleep 0.5"));
+        assert!(responder.reauthenticate());
+        assert!(!responder.reauthenticate());
+        wait_for_exit(&responder);
+        assert!(!responder.reauthentication_failed());
+        assert!(responder.reauthenticate());
+        wait_for_exit(&responder);
+    }
+
+    #[test]
+    fn failed_reauthentication_is_reported_once() {
+        let mut responder = Responder::new(0.5, 0, 0.3, 0, command("sh -c 'exit 1'"));
+        assert!(responder.reauthenticate());
+        wait_for_exit(&responder);
+        assert!(responder.reauthentication_failed());
+        assert!(!responder.reauthentication_failed());
+    }
+
+    #[test]
+    fn command_is_split_like_a_shell() {
+        assert_eq!(
+            command("zenity --password --title 'Confirm it is you'"),
+            vec!["zenity", "--password", "--title", "Confirm it is you"]
+        );
+        assert!(command("").is_empty());
+        assert!(parse_command("zenity --title 'unbalanced").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+/**
+ * This module shows a StatusNotifierItem tray icon reflecting the phase and
+ * the score of the user, with a menu to pause the collection and to show the
+ * last status.
+ */
+use ksni::blocking::{Handle, TrayMethods};
+use ksni::menu::{CheckmarkItem, StandardItem};
+use ksni::{MenuItem, ToolTip};
+
+use crate::data_collector;
+use crate::status::notification;
+use crate::status::phase::Phase;
+use crate::status::Status;
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+const TRAY_ID: &str = "x11-sentinel-client";
+const TRAY_TITLE: &str = "X11 Sentinel Client";
+
+/// Icons shown while no status is known or the profile is not verified yet,
+/// when the score is above the lock threshold and when it is below.
+const UNKNOWN_ICON: &str = "security-medium";
+const HIGH_SCORE_ICON: &str = "security-high";
+const LOW_SCORE_ICON: &str = "security-low";
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// State of the tray icon, owned by the tray service.
+struct Indicator {
+    status: Option<Status>,
+    lock_threshold: f64,
+}
+
+impl ksni::Tray for Indicator {
+    fn id(&self) -> String {
+        return String::from(TRAY_ID);
+    }
+
+    fn title(&self) -> String {
+        return String::from(TRAY_TITLE);
+    }
+
+    fn icon_name(&self) -> String {
+        let icon = match &self.status {
+            Some(status) if status.phase == Phase::Verify => {
+                match status.value < self.lock_threshold {
+                    true => LOW_SCORE_ICON,
+                    false => HIGH_SCORE_ICON,
+                }
+            }
+            _ => UNKNOWN_ICON,
+        };
+        return String::from(icon);
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        let mut description = match &self.status {
+            Some(status) => format!("Phase: {}, score: {}", status.phase, status.value),
+            None => String::from("No status yet"),
+        };
+        if data_collector::is_paused() {
+            description.push_str(", collection paused");
+        }
+        ToolTip {
+            title: String::from(TRAY_TITLE),
+            description,
+            ..Default::default()
+        }
+    }
+
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        vec![
+            CheckmarkItem {
+                label: String::from("Pause collection"),
+                checked: data_collector::is_paused(),
+                activate: Box::new(|_indicator: &mut Indicator| {
+                    match data_collector::is_paused() {
+                        true => data_collector::resume(),
+                        false => data_collector::pause(),
+                    }
+                }),
+                ..Default::default()
+            }
+            .into(),
+            StandardItem {
+                label: String::from("Show last status"),
+                enabled: self.status.is_some(),
+                activate: Box::new(|indicator: &mut Indicator| {
+                    if let Some(status) = &indicator.status {
+                        notification::show_details(&notification::format_status(status));
+                    }
+                }),
+                ..Default::default()
+            }
+            .into(),
+        ]
+    }
+}
+
+/// Tray icon of the application. Updates are ignored if the tray icon is
+/// disabled or could not be shown.
+pub struct TrayIcon {
+    handle: Option<Handle<Indicator>>,
+}
+
+impl TrayIcon {
+    /// Constructor for the `TrayIcon` object. The tray icon is only shown if
+    /// it is enabled and a StatusNotifierItem host is running.
+    pub fn new(enabled: bool, lock_threshold: f64) -> TrayIcon {
+        if !enabled {
+            return TrayIcon { handle: None };
+        }
+        let indicator = Indicator {
+            status: None,
+            lock_threshold,
+        };
+        match indicator.spawn() {
+            Ok(handle) => {
+                return TrayIcon {
+                    handle: Some(handle),
+                }
+            }
+            Err(error) => {
+                println!("Could not show tray icon: {}", error);
+                return TrayIcon { handle: None };
+            }
+        }
+    }
+
+    /// Show the given status and lock threshold.
+    pub fn update(&self, status: &Status, lock_threshold: f64) -> () {
+        if let Some(handle) = &self.handle {
+            handle.update(|indicator| {
+                indicator.status = Some(status.clone());
+                indicator.lock_threshold = lock_threshold;
+            });
+        }
+    }
+}
@@ -0,0 +1,89 @@
+/**
+ * This module notifies the systemd service manager about the state of the
+ * application. Nothing is sent when the application is not started by
+ * systemd.
+ */
+use sd_notify::NotifyState;
+use std::time::{Duration, Instant};
+
+use crate::health;
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// Keeps the systemd watchdog from restarting the application while it makes
+/// progress.
+pub struct Watchdog {
+    interval: Option<Duration>,
+    last_ping: Option<Instant>,
+}
+
+impl Watchdog {
+    /// Constructor for the `Watchdog` object. The watchdog is disabled if the
+    /// service has no `WatchdogSec` configured.
+    pub fn new() -> Watchdog {
+        let mut usec = 0;
+        let interval = match sd_notify::watchdog_enabled(false, &mut usec) {
+            // Ping twice per watchdog timeout, as recommended by systemd.
+            true => Some(Duration::from_micros(usec / 2)),
+            false => None,
+        };
+        Watchdog {
+            interval,
+            last_ping: None,
+        }
+    }
+
+    /// Maximum time between two calls of `ping`, `None` if the watchdog is
+    /// disabled.
+    pub fn interval(&self) -> Option<Duration> {
+        return self.interval;
+    }
+
+    /// Ping the watchdog if the ping interval elapsed. The watchdog is not
+    /// pinged while a subsystem of the application is dead or the status
+    /// thread is stuck, so that systemd restarts the application. An
+    /// unreachable status server does not count as stuck.
+    pub fn ping(&mut self) -> () {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        if let Some(last_ping) = self.last_ping {
+            if last_ping.elapsed() < interval {
+                return;
+            }
+        }
+        if !health::is_ready() {
+            return;
+        }
+        notify(&[NotifyState::Watchdog]);
+        self.last_ping = Some(Instant::now());
+    }
+}
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Tell systemd that the application finished starting up.
+pub fn notify_ready() -> () {
+    notify(&[NotifyState::Ready]);
+}
+
+/// Tell systemd the human readable status of the application.
+pub fn notify_status(status: &str) -> () {
+    notify(&[NotifyState::Status(status)]);
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+fn notify(state: &[NotifyState]) -> () {
+    match sd_notify::notify(false, state) {
+        Ok(()) => (),
+        Err(error) => println!("Could not notify systemd: {}", error),
+    }
+}
@@ -0,0 +1,62 @@
+/**
+ * Private D-Bus daemon for the tests of the modules talking to D-Bus
+ * services, so that the tests neither need nor touch the buses of the host.
+ */
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::process::{Child, Command, Stdio};
+use zbus::blocking::{Connection, ConnectionBuilder};
+
+//==============================================================================
+// Structs
+//==============================================================================
+
+/// A `dbus-daemon` running for the lifetime of the object.
+pub struct TestBus {
+    daemon: Child,
+    address: String,
+}
+
+impl TestBus {
+    /// Start a private bus. `None` is returned if `dbus-daemon` is not
+    /// installed, so that the calling test can be skipped.
+    pub fn start() -> Option<TestBus> {
+        let daemon = Command::new("dbus-daemon")
+            .args(["--session", "--print-address", "--nofork"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut daemon = match daemon {
+            Ok(daemon) => daemon,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                println!("Skipping test, dbus-daemon is not installed");
+                return None;
+            }
+            Err(error) => panic!("Could not start dbus-daemon: {}", error),
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        return Some(TestBus {
+            daemon,
+            address: String::from(address.trim()),
+        });
+    }
+
+    /// Open a new connection to the bus.
+    pub fn connect(&self) -> Connection {
+        return self.builder().build().unwrap();
+    }
+
+    /// Builder for a new connection to the bus.
+    pub fn builder(&self) -> ConnectionBuilder<'static> {
+        return ConnectionBuilder::address(self.address.as_str()).unwrap();
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
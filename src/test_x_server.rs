@@ -0,0 +1,129 @@
+/**
+ * Minimal X server for the tests and benchmarks of the pointer queries, so
+ * that they do not need a display.
+ *
+ * The server answers the setup request and the `QueryPointer` requests of a
+ * single little endian client. Other requests are ignored. The replies report
+ * the sequence number of the request as the x coordinate of the pointer, so
+ * that the order of the replies can be checked.
+ */
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use x11rb::rust_connection::{DefaultStream, RustConnection};
+
+//==============================================================================
+// Constants
+//==============================================================================
+
+pub const ROOT_WINDOW: u32 = 0x100;
+
+const QUERY_POINTER_OPCODE: u8 = 38;
+
+//==============================================================================
+// Public functions
+//==============================================================================
+
+/// Connect to a new mock X server.
+pub fn connect() -> RustConnection {
+    return connect_closing_after(None);
+}
+
+/// Connect to a new mock X server closing the connection after the given
+/// number of requests, `None` to keep it open.
+pub fn connect_closing_after(requests: Option<u16>) -> RustConnection {
+    let (client, server) = UnixStream::pair().unwrap();
+    thread::spawn(move || serve(server, requests));
+    let stream = DefaultStream::from_unix_stream(client).unwrap();
+    return RustConnection::connect_to_stream(stream, 0).unwrap();
+}
+
+//==============================================================================
+// Internal functions
+//==============================================================================
+
+/// Serve a single client until it disconnects or the request limit is
+/// reached.
+fn serve(stream: UnixStream, requests: Option<u16>) -> () {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut writer = BufWriter::new(stream);
+
+    // Setup request, followed by the padded authorization name and data.
+    let mut request = [0; 12];
+    reader.read_exact(&mut request).unwrap();
+    let name_length = u16::from_le_bytes([request[6], request[7]]) as usize;
+    let data_length = u16::from_le_bytes([request[8], request[9]]) as usize;
+    let mut authorization = vec![0; pad(name_length) + pad(data_length)];
+    reader.read_exact(&mut authorization).unwrap();
+    writer.write_all(&get_setup()).unwrap();
+    writer.flush().unwrap();
+
+    let mut sequence_number: u16 = 0;
+    loop {
+        if Some(sequence_number) == requests {
+            return;
+        }
+        // Send the replies once every received request is answered.
+        if reader.buffer().is_empty() {
+            writer.flush().unwrap();
+        }
+        let mut header = [0; 4];
+        if reader.read_exact(&mut header).is_err() {
+            return;
+        }
+        let length = u16::from_le_bytes([header[2], header[3]]) as usize * 4;
+        let mut body = vec![0; length - 4];
+        reader.read_exact(&mut body).unwrap();
+        sequence_number = sequence_number.wrapping_add(1);
+
+        if header[0] == QUERY_POINTER_OPCODE {
+            let mut reply = vec![1, 1];
+            reply.extend_from_slice(&sequence_number.to_le_bytes());
+            reply.extend_from_slice(&0u32.to_le_bytes());
+            reply.extend_from_slice(&ROOT_WINDOW.to_le_bytes());
+            reply.extend_from_slice(&0u32.to_le_bytes());
+            // Root and window coordinates.
+            let x = sequence_number & 0x7fff;
+            for value in [x, 200, x, 200] {
+                reply.extend_from_slice(&value.to_le_bytes());
+            }
+            reply.resize(32, 0);
+            writer.write_all(&reply).unwrap();
+        }
+    }
+}
+
+/// Setup reply describing a single screen without visuals.
+fn get_setup() -> Vec<u8> {
+    let mut setup = vec![1, 0];
+    setup.extend_from_slice(&11u16.to_le_bytes());
+    setup.extend_from_slice(&0u16.to_le_bytes());
+    // Length of the rest in 4 byte units.
+    setup.extend_from_slice(&18u16.to_le_bytes());
+    setup.extend_from_slice(&0u32.to_le_bytes());
+    setup.extend_from_slice(&0x0040_0000u32.to_le_bytes());
+    setup.extend_from_slice(&0x001f_ffffu32.to_le_bytes());
+    setup.extend_from_slice(&0u32.to_le_bytes());
+    // Vendor length, maximum request length, screens and pixmap formats.
+    setup.extend_from_slice(&0u16.to_le_bytes());
+    setup.extend_from_slice(&u16::MAX.to_le_bytes());
+    setup.extend_from_slice(&[1, 0]);
+    // Byte orders, bitmap format, keycodes and padding.
+    setup.extend_from_slice(&[0, 0, 32, 32, 8, 255, 0, 0, 0, 0]);
+
+    // Root window, colormap, white and black pixels and input masks.
+    setup.extend_from_slice(&ROOT_WINDOW.to_le_bytes());
+    setup.extend_from_slice(&[0; 16]);
+    // Size in pixels and millimeters, installed colormaps.
+    for value in [1920u16, 1080, 508, 286, 1, 1] {
+        setup.extend_from_slice(&value.to_le_bytes());
+    }
+    // Root visual, backing stores, save unders, root depth and depths.
+    setup.extend_from_slice(&[0, 0, 0, 0, 0, 0, 24, 0]);
+    return setup;
+}
+
+/// Length padded to a multiple of 4 bytes.
+fn pad(length: usize) -> usize {
+    return length.div_ceil(4) * 4;
+}